@@ -0,0 +1,14 @@
+// The loop timed by the `compute` bench, kept in its own file so the integration tests can
+// check it against the library's `s_sum` (tests/integration.rs, bench_loop_matches_library).
+pub fn collatz_s_sum(k: u32, l: u32, idx: usize) -> u32 {
+    let mask: u64 = (1u64 << k) - 1;
+    let mut m = ((idx as u64) << 1) | 1;
+    let mut s: u64 = 0;
+    for _ in 0..l {
+        let t = 3u64.wrapping_mul(m & mask).wrapping_add(1);
+        let e = t.trailing_zeros() as u64;
+        s += e;
+        m = (t >> e) & mask;
+    }
+    s.min(u32::MAX as u64) as u32
+}
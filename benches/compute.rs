@@ -1,17 +1,8 @@
 use criterion::{criterion_group, criterion_main, Criterion, Throughput, BenchmarkId};
 
-fn collatz_s_sum(k: u32, l: u32, idx: usize) -> u32 {
-    let mask: u64 = (1u64 << k) - 1;
-    let mut m = ((idx as u64) << 1) | 1;
-    let mut s: u64 = 0;
-    for _ in 0..l {
-        let t = 3u64.wrapping_mul(m & mask).wrapping_add(1);
-        let e = t.trailing_zeros() as u64;
-        s += e;
-        m = (t >> e) & mask;
-    }
-    s.min(u32::MAX as u64) as u32
-}
+#[path = "common/s_sum.rs"]
+mod s_sum;
+use s_sum::collatz_s_sum;
 
 fn bench_collatz(c: &mut Criterion) {
     let mut group = c.benchmark_group("collatz_s_sum");
@@ -4,7 +4,8 @@ use std::{process::Command, env};
 fn main() {
     // git rev
     let git = Command::new("git").args(["rev-parse", "--short", "HEAD"]).output();
-    if let Ok(o) = git {
+    // outside a git checkout the command fails with empty stdout; record the placeholder instead
+    if let Some(o) = git.ok().filter(|o| o.status.success()) {
         println!("cargo:rustc-env=BUILD_GIT_REV={}", String::from_utf8_lossy(&o.stdout).trim());
     } else {
         println!("cargo:rustc-env=BUILD_GIT_REV=unknown");
@@ -12,7 +13,7 @@ fn main() {
     // rustc -V
     let rv = Command::new(env::var("RUSTC").unwrap_or_else(|_| "rustc".into()))
         .arg("-V").output();
-    if let Ok(o) = rv {
+    if let Some(o) = rv.ok().filter(|o| o.status.success()) {
         println!("cargo:rustc-env=BUILD_RUSTC={}", String::from_utf8_lossy(&o.stdout).trim());
     } else {
         println!("cargo:rustc-env=BUILD_RUSTC=unknown");
@@ -0,0 +1,1371 @@
+//! Table format and S-sum core shared by the `collatz_cert` binary, benches and tests.
+//!
+//! Every multi-byte field of the on-disk format is little-endian, whatever the host.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Header {
+    pub magic: [u8; 4],
+    pub ver: u32,
+    pub k: u32,
+    pub l: u32,
+    pub count: u64,
+    /// Bit flags (FLAG_*); stored in the first four formerly reserved bytes
+    pub flags: u32,
+    /// Version of the S-sum definition that produced the entries (`ALGO_VER`); 0 in tables
+    /// written before the field existed, which used the same definition as version 1
+    pub algo_ver: u16,
+    pub _reserved: [u8; 2],
+}
+
+/// Header flag: the table covers all residues mod 2^k, not just the odd ones.
+pub const FLAG_ALL_RESIDUES: u32 = 1;
+/// Header flag: a deliberately truncated table (`gen --max-index`) holding only the first
+/// `count` entries; it never establishes a full pass.
+pub const FLAG_PARTIAL: u32 = 2;
+pub const KNOWN_FLAGS: u32 = FLAG_ALL_RESIDUES | FLAG_PARTIAL;
+
+/// S-sum definition implemented by `steps`: the accelerated map `(3m+1)/2^e` mod 2^k,
+/// S being the sum of the exponents `e`. Bump it whenever that definition changes.
+pub const ALGO_VER: u16 = 1;
+
+/// Format version whose entries carry the residue reached after `l` steps next to S
+/// (`s: u32, m: u32`), so a table can be extended to a larger `l` without starting over.
+pub const VER_STATE: u32 = 3;
+
+/// Run-length encoded format version: the body is `(s: u32, run: u32)` pairs, each run at
+/// least 1 and the runs summing to `count`. `count` is still the number of residues, so
+/// the file length depends on the data; the sha256 trailer covers the encoded body.
+pub const VER_RLE: u32 = 4;
+
+impl Header {
+    /// Parse and validate the 32-byte header at the start of `data`.
+    pub fn parse(data: &[u8]) -> anyhow::Result<Header> {
+        anyhow::ensure!(data.len() >= 64, "file too small");
+        Header::decode(data[..32].try_into()?)
+    }
+
+    /// Validate a bare 32-byte header; unlike `parse` this needs no body behind it.
+    pub fn decode(data: &[u8; 32]) -> anyhow::Result<Header> {
+        anyhow::ensure!(&data[0..4] == b"CALT", "bad magic");
+        let ver = u32::from_le_bytes(data[4..8].try_into()?);
+        anyhow::ensure!((1..=VER_RLE).contains(&ver), "bad version");
+        let flags = u32::from_le_bytes(data[24..28].try_into()?);
+        anyhow::ensure!(flags & !KNOWN_FLAGS == 0, "bad header: unknown flags {flags:#x}");
+        let h = Header {
+            magic: *b"CALT",
+            ver,
+            k: u32::from_le_bytes(data[8..12].try_into()?),
+            l: u32::from_le_bytes(data[12..16].try_into()?),
+            count: u64::from_le_bytes(data[16..24].try_into()?),
+            flags,
+            algo_ver: u16::from_le_bytes(data[28..30].try_into()?),
+            _reserved: data[30..32].try_into()?,
+        };
+        if h.partial() {
+            anyhow::ensure!((1..=62).contains(&h.k), "bad header: k={} out of range", h.k);
+            let full = h.residues().count(h.k);
+            anyhow::ensure!(
+                (1..full).contains(&h.count),
+                "bad header: partial table count={} must be in [1, {full}) for k={}", h.count, h.k
+            );
+        } else {
+            check_count(h.k, h.count, h.residues())?;
+        }
+        anyhow::ensure!(ver != VER_STATE || h.k <= 32, "bad header: k={} too large for ver=3 state entries", h.k);
+        Ok(h)
+    }
+
+    /// Bytes per table entry (per `(s, run)` pair for ver=4).
+    pub fn width(&self) -> usize {
+        match self.ver { 1 => 2, VER_STATE | VER_RLE => 8, _ => 4 }
+    }
+
+    /// Body length in bytes, when the header alone determines it (not for ver=4).
+    pub fn body_len(&self) -> Option<usize> {
+        (self.ver != VER_RLE).then(|| self.count as usize * self.width())
+    }
+
+    /// Whether the entries were computed with this build's S-sum definition.
+    pub fn algo_compatible(&self) -> bool {
+        self.algo_ver == 0 || self.algo_ver == ALGO_VER
+    }
+
+    pub fn residues(&self) -> Residues {
+        if self.flags & FLAG_ALL_RESIDUES != 0 { Residues::All } else { Residues::Odd }
+    }
+
+    /// Whether `count` is only a prefix of the residues (FLAG_PARTIAL).
+    pub fn partial(&self) -> bool {
+        self.flags & FLAG_PARTIAL != 0
+    }
+}
+
+/// One field of the 32-byte table header; all integers are little-endian.
+pub struct HeaderField {
+    pub name: &'static str,
+    pub offset: usize,
+    pub size: usize,
+    pub meaning: &'static str,
+}
+
+/// Layout of the header read by `Header::decode` and written by `write_header`; a test
+/// writes a header with distinct field values and checks each one at its listed offset.
+pub const HEADER_FIELDS: [HeaderField; 8] = [
+    HeaderField { name: "magic", offset: 0, size: 4, meaning: "ASCII \"CALT\"" },
+    HeaderField { name: "ver", offset: 4, size: 4, meaning: "format version, see below" },
+    HeaderField { name: "k", offset: 8, size: 4, meaning: "modulus bits: residues mod 2^k" },
+    HeaderField { name: "l", offset: 12, size: 4, meaning: "steps of the accelerated map summed per entry" },
+    HeaderField { name: "count", offset: 16, size: 8, meaning: "entries: 2^(k-1) odd residues, 2^k with FLAG_ALL_RESIDUES, fewer with FLAG_PARTIAL" },
+    HeaderField { name: "flags", offset: 24, size: 4, meaning: "bit flags; unknown bits are rejected" },
+    HeaderField { name: "algo_ver", offset: 28, size: 2, meaning: "S-sum definition version; 0 = written before the field, same as 1" },
+    HeaderField { name: "reserved", offset: 30, size: 2, meaning: "zero, ignored" },
+];
+
+/// Plain-text description of the table file format, built from the constants and layout
+/// the parser uses (`collatz_cert format-spec`).
+pub fn format_spec() -> String {
+    use std::fmt::Write as _;
+    let mut out = String::new();
+    let header = |ver| Header { magic: *b"CALT", ver, k: 0, l: 0, count: 0, flags: 0, algo_ver: 0, _reserved: [0; 2] };
+    let width = |ver| header(ver).width();
+    let len: usize = HEADER_FIELDS.iter().map(|f| f.size).sum();
+    let _ = writeln!(out, "collatz_cert table format (versions 1..={VER_RLE}, S-sum algo_ver={ALGO_VER})");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "file = header ({len} bytes) | body | trailer (32 bytes); gzip-compressed files (1f 8b) are accepted");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "header, little-endian:");
+    let _ = writeln!(out, "  {:<7} {:<5} {:<9} meaning", "offset", "size", "field");
+    for f in &HEADER_FIELDS {
+        let _ = writeln!(out, "  {:<7} {:<5} {:<9} {}", f.offset, f.size, f.name, f.meaning);
+    }
+    let _ = writeln!(out, "  flags: {FLAG_ALL_RESIDUES:#x} = FLAG_ALL_RESIDUES (entry i is residue i; otherwise residue 2i+1)");
+    let _ = writeln!(out, "         {FLAG_PARTIAL:#x} = FLAG_PARTIAL (only entries [0, count) of the residues; never a full certificate)");
+    let _ = writeln!(out, "  ver={VER_STATE} requires k <= 32");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "body, entries in index order:");
+    let _ = writeln!(out, "  ver=1: count x u16 S ({} bytes each)", width(1));
+    let _ = writeln!(out, "  ver=2: count x u32 S ({} bytes each)", width(2));
+    let _ = writeln!(out, "  ver={VER_STATE}: count x (s: u32, m: u32), m the odd residue reached after l steps ({} bytes each)", width(VER_STATE));
+    let _ = writeln!(out, "  ver={VER_RLE}: (s: u32, run: u32) pairs ({} bytes each), run >= 1, runs summing to count", width(VER_RLE));
+    let _ = writeln!(out, "  S = sum of the exponents e of the map m -> (3m+1)/2^e mod 2^k over l steps");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "trailer: sha256 of the body bytes (the manifest's sha256_table_hex); nothing may follow it");
+    out
+}
+
+/// Which residues mod 2^k a table covers.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Residues {
+    /// Odd residues only: index i is the residue (i<<1)|1; count = 2^(k-1)
+    Odd,
+    /// All residues: index i is the residue i itself; count = 2^k
+    All,
+}
+
+impl Residues {
+    pub fn count(self, k: u32) -> u64 {
+        match self { Residues::Odd => 1u64 << (k - 1), Residues::All => 1u64 << k }
+    }
+
+    pub fn flag(self) -> u32 {
+        match self { Residues::Odd => 0, Residues::All => FLAG_ALL_RESIDUES }
+    }
+
+    /// Name recorded as the manifest's `residue_form`.
+    pub fn form(self) -> &'static str {
+        match self { Residues::Odd => "odd", Residues::All => "all" }
+    }
+
+    pub fn from_form(form: &str) -> Option<Self> {
+        match form { "odd" => Some(Residues::Odd), "all" => Some(Residues::All), _ => None }
+    }
+
+    #[inline]
+    pub fn s_sum(self, mask: u64, l: u32, idx: u64) -> u32 {
+        match self { Residues::Odd => s_sum(mask, l, idx), Residues::All => s_sum_all(mask, l, idx) }
+    }
+
+    /// S-sum of entry `idx` together with the odd residue its trajectory reaches.
+    #[inline]
+    pub fn s_sum_state(self, mask: u64, l: u32, idx: u64) -> (u32, u64) {
+        match self {
+            Residues::Odd => steps(mask, l, (idx << 1) | 1),
+            Residues::All => s_sum_all_state(mask, l, idx),
+        }
+    }
+}
+
+/// Certificate manifest written next to a table by `gen` and checked by `verify`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub k: u32,
+    pub l: u32,
+    pub count: u64,
+    pub min_s: u32,
+    /// Largest S (absent in manifests from older gens or resumed older checkpoints)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_s: Option<u32>,
+    /// Mean S, from the exact integer sum
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mean_s: Option<f64>,
+    /// Residues with negative drift, `s_i < l*c` (for log2(3): `2^s_i < 3^l`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub neg_drift_count: Option<u64>,
+    pub eps: f64,
+    pub threshold: u32,
+    pub pass: bool,
+    pub sha256_table_hex: String,
+    pub sha256_exec_hex: String,
+    pub generator_cmdline: String,
+    /// Normalized invocation that regenerates the table (no output paths or threads)
+    #[serde(default)]
+    pub reproduce_cmd: String,
+    pub pkg_version: String,
+    pub build_git_rev: String,
+    pub build_rustc: String,
+    pub os_arch: String,
+    pub gen_ts: String,
+    #[serde(default)]
+    pub file_ver: u32,
+    /// Table covers all residues mod 2^k (gen --include-even)
+    #[serde(default)]
+    pub include_even: bool,
+    /// Residues are taken mod 2^modulus_bits (always k; absent in older manifests)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modulus_bits: Option<u32>,
+    /// Starting value of entry i: "odd" is (i<<1)|1, "all" is i itself (see `Residues::form`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub residue_form: Option<String>,
+    /// Drift constant c used for threshold and eps (log2(3) unless gen --log-constant)
+    #[serde(default = "log2_3")]
+    pub log_constant: f64,
+    /// Peak resident memory of the gen process, in bytes (absent where it cannot be measured)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_rss_bytes: Option<u64>,
+    /// Free-text provenance note (gen --note); ignored by verify
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// sha256 of the canonical manifest bytes with this field absent (see `manifest_sha256`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest_sha256: Option<String>,
+}
+
+#[inline]
+pub fn log2_3() -> f64 { 3f64.log2() }
+
+/// `log2(3)` to `digits` decimal places (truncated), computed exactly rather than from
+/// the f64 in `log2_3`: the binary digits come from repeatedly squaring 3/2 in fixed
+/// point, with enough guard bits that rounding never reaches the digits printed.
+pub fn log2_3_digits(digits: usize) -> String {
+    // fraction bits needed for `digits` decimals, plus slack for truncation
+    let n = (digits as f64 * std::f64::consts::LOG2_10).ceil() as usize + 32;
+    let p = n + 64;
+    // x in [1, 2) as the integer x * 2^p
+    let mut x = big_shl(&[3], p - 1);
+    // fraction bits of log2(3) - 1 = log2(3/2), most significant first
+    let mut frac = vec![0u32; n.div_ceil(32)];
+    for i in 0..n {
+        x = big_shr(&big_mul(&x, &x), p);
+        if big_bits(&x) > p + 1 {
+            x = big_shr(&x, 1);
+            let bit = n - 1 - i;
+            frac[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+    let mut out = String::with_capacity(digits + 2);
+    out.push_str("1.");
+    for _ in 0..digits {
+        // frac * 10: the bits above position n are the next decimal digit
+        let mut carry = 0u64;
+        for limb in frac.iter_mut() {
+            let v = *limb as u64 * 10 + carry;
+            *limb = v as u32;
+            carry = v >> 32;
+        }
+        let digit = if n.is_multiple_of(32) {
+            carry as u32
+        } else {
+            let (top, bits) = (n / 32, n % 32);
+            let d = (frac[top] >> bits) as u64 | carry << (32 - bits);
+            frac[top] &= (1 << bits) - 1;
+            d as u32
+        };
+        out.push(char::from_digit(digit, 10).expect("decimal digit"));
+    }
+    out
+}
+
+fn big_mul(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut out = vec![0u32; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, &y) in b.iter().enumerate() {
+            let v = out[i + j] as u64 + x as u64 * y as u64 + carry;
+            out[i + j] = v as u32;
+            carry = v >> 32;
+        }
+        out[i + b.len()] = carry as u32;
+    }
+    out
+}
+
+fn big_shl(a: &[u32], s: usize) -> Vec<u32> {
+    let (limbs, bits) = (s / 32, s % 32);
+    let mut out = vec![0u32; limbs];
+    let mut carry = 0u32;
+    for &x in a {
+        out.push(if bits == 0 { x } else { x << bits | carry });
+        carry = if bits == 0 { 0 } else { x >> (32 - bits) };
+    }
+    out.push(carry);
+    out
+}
+
+/// `a >> s`, without high zero limbs so repeated squaring does not grow the operands.
+fn big_shr(a: &[u32], s: usize) -> Vec<u32> {
+    let (limbs, bits) = (s / 32, s % 32);
+    let mut out: Vec<u32> = (limbs..a.len())
+        .map(|i| {
+            let hi = if bits == 0 { 0 } else { a.get(i + 1).map_or(0, |&h| h << (32 - bits)) };
+            a[i] >> bits | hi
+        })
+        .collect();
+    while out.last() == Some(&0) {
+        out.pop();
+    }
+    out
+}
+
+fn big_bits(a: &[u32]) -> usize {
+    a.iter().rposition(|&x| x != 0).map_or(0, |i| 32 * i + 32 - a[i].leading_zeros() as usize)
+}
+
+/// Drift margin `eps = s/l - c` of an S-sum over `l` steps. The empty block `l = 0` has
+/// `s = 0` and no drift, so its margin is `-c`.
+#[inline]
+pub fn drift_eps(s: u32, l: u32, c: f64) -> f64 {
+    if l == 0 { -c } else { s as f64 / l as f64 - c }
+}
+
+/// `log2(3)` in fixed point with 94 fraction bits, rounded down.
+const LOG2_3_FIX94: u128 = 0x6570_068e_7ef5_a1e7_e802_c482;
+
+/// Bit length of `3^l`, i.e. the least `s` with `2^s > 3^l`: `floor(l*log2(3)) + 1`. The
+/// floor is read off the fixed-point bounds `l*F <= l*log2(3) < l*(F+1)` (over `2^94`), which
+/// agree unless `l*log2(3)` is within `2^-62` of an integer. No `u32` `l` comes that close:
+/// the nearest is the convergent denominator 397573379, about 1.5e-10 off, and no later
+/// convergent is below 2^32. So every call is O(1); the exact power is only a backstop.
+pub fn pow3_bit_length(l: u32) -> u64 {
+    let lo = (l as u128 * LOG2_3_FIX94) >> 94;
+    let hi = (l as u128 * (LOG2_3_FIX94 + 1)) >> 94;
+    if lo == hi {
+        return lo as u64 + 1;
+    }
+    let mut limbs = vec![1u32];
+    for _ in 0..l {
+        let mut carry = 0u64;
+        for d in limbs.iter_mut() {
+            let v = *d as u64 * 3 + carry;
+            *d = v as u32;
+            carry = v >> 32;
+        }
+        if carry > 0 {
+            limbs.push(carry as u32);
+        }
+    }
+    let top = *limbs.last().expect("non-empty");
+    32 * (limbs.len() as u64 - 1) + (32 - top.leading_zeros()) as u64
+}
+
+/// Negative drift means `s < neg_drift_bound(l, c)`. For `c = log2(3)` this is the exact
+/// `2^s < 3^l`; other constants compare against `ceil(l*c)`. At `l = 0` every entry has
+/// `s = 0` and the margin `drift_eps = -c`, so it counts as negative drift for every `c`,
+/// in line with that eps rather than with `2^0 = 3^0`.
+pub fn neg_drift_bound(l: u32, c: f64) -> u64 {
+    if l == 0 {
+        1
+    } else if c == log2_3() {
+        pow3_bit_length(l)
+    } else {
+        (l as f64 * c).ceil() as u64
+    }
+}
+
+/// Manifest bytes that depend only on content, for hashing or signing: see
+/// `canonical_json_bytes`.
+pub fn canonical_manifest_bytes(m: &Manifest) -> Vec<u8> {
+    canonical_json_bytes(m).expect("manifest serializes to JSON")
+}
+
+/// Hex sha256 over `canonical_manifest_bytes` of `m` without its `manifest_sha256` field,
+/// i.e. the value gen stores in that field.
+pub fn manifest_sha256(m: &Manifest) -> String {
+    let bytes = canonical_json_bytes(&Manifest { manifest_sha256: None, ..m.clone() })
+        .expect("manifest serializes to JSON");
+    Sha256::digest(bytes).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// JSON Schema (draft 2020-12) for `Manifest`. Written out by hand, as no schema derive is
+/// available offline; a test checks its property list against a serialized manifest, so a
+/// new field that is not added here fails the build's tests.
+pub fn manifest_schema() -> serde_json::Value {
+    use serde_json::json;
+    let u32_ = json!({"type": "integer", "minimum": 0, "maximum": u32::MAX});
+    let u64_ = json!({"type": "integer", "minimum": 0});
+    let hex = json!({"type": "string", "pattern": "^[0-9a-f]{64}$"});
+    let text = json!({"type": "string"});
+    // (field, schema, required): required fields are the ones without a serde default
+    let fields = [
+        ("k", u32_.clone(), true),
+        ("l", u32_.clone(), true),
+        ("count", u64_.clone(), true),
+        ("min_s", u32_.clone(), true),
+        ("max_s", u32_.clone(), false),
+        ("mean_s", json!({"type": "number"}), false),
+        ("neg_drift_count", u64_.clone(), false),
+        ("eps", json!({"type": "number"}), true),
+        ("threshold", u32_.clone(), true),
+        ("pass", json!({"type": "boolean"}), true),
+        ("sha256_table_hex", hex.clone(), true),
+        // "unknown" when the executable could not be read
+        ("sha256_exec_hex", text.clone(), true),
+        ("generator_cmdline", text.clone(), true),
+        ("reproduce_cmd", text.clone(), false),
+        ("pkg_version", text.clone(), true),
+        ("build_git_rev", text.clone(), true),
+        ("build_rustc", text.clone(), true),
+        ("os_arch", text.clone(), true),
+        ("gen_ts", text.clone(), true),
+        ("file_ver", u32_.clone(), false),
+        ("include_even", json!({"type": "boolean"}), false),
+        ("modulus_bits", u32_, false),
+        ("residue_form", json!({"enum": ["odd", "all"]}), false),
+        ("log_constant", json!({"type": "number", "exclusiveMinimum": 0}), false),
+        ("peak_rss_bytes", u64_.clone(), false),
+        ("notes", text, false),
+        ("manifest_sha256", hex, false),
+    ];
+    let properties: serde_json::Map<String, serde_json::Value> =
+        fields.iter().map(|(name, schema, _)| (name.to_string(), schema.clone())).collect();
+    let required: Vec<&str> = fields.iter().filter(|f| f.2).map(|f| f.0).collect();
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "collatz_cert manifest",
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+/// Compact JSON with object keys sorted bytewise and floats in Rust's shortest
+/// round-trip form (`{:?}`), so the bytes do not depend on struct field order.
+pub fn canonical_json_bytes<T: Serialize>(value: &T) -> anyhow::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    write_canonical(&serde_json::to_value(value)?, &mut out);
+    Ok(out)
+}
+
+fn write_canonical(v: &serde_json::Value, out: &mut Vec<u8>) {
+    use serde_json::Value;
+    match v {
+        Value::Null | Value::Bool(_) | Value::String(_) => out.extend_from_slice(v.to_string().as_bytes()),
+        Value::Number(n) => match (n.as_u64(), n.as_i64(), n.as_f64()) {
+            (Some(u), _, _) => out.extend_from_slice(u.to_string().as_bytes()),
+            (None, Some(i), _) => out.extend_from_slice(i.to_string().as_bytes()),
+            (None, None, Some(f)) => out.extend_from_slice(format!("{f:?}").as_bytes()),
+            _ => out.extend_from_slice(n.to_string().as_bytes()),
+        },
+        Value::Array(items) => {
+            out.push(b'[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 { out.push(b','); }
+                write_canonical(item, out);
+            }
+            out.push(b']');
+        }
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push(b'{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 { out.push(b','); }
+                out.extend_from_slice(Value::String(key.clone()).to_string().as_bytes());
+                out.push(b':');
+                write_canonical(&map[key], out);
+            }
+            out.push(b'}');
+        }
+    }
+}
+
+/// Write the 32-byte little-endian table header.
+pub fn write_header<W: Write>(w: &mut W, h: &Header) -> std::io::Result<()> {
+    w.write_all(&h.magic)?;
+    w.write_all(&h.ver.to_le_bytes())?;
+    w.write_all(&h.k.to_le_bytes())?;
+    w.write_all(&h.l.to_le_bytes())?;
+    w.write_all(&h.count.to_le_bytes())?;
+    w.write_all(&h.flags.to_le_bytes())?;
+    w.write_all(&h.algo_ver.to_le_bytes())?;
+    w.write_all(&h._reserved)?;
+    Ok(())
+}
+
+/// Writes a complete, valid table file (header, body, sha256 trailer) from a sequence of
+/// S values, e.g. for test fixtures or external tools:
+///
+/// ```no_run
+/// # use collatz_cert::TableBuilder;
+/// let values = (0..8u64).map(|i| collatz_cert::s_sum(15, 8, i));
+/// let sha256 = TableBuilder::new(4, 8).version(1).write_file("table_v1_k4_l8.bin".as_ref(), values)?;
+/// # anyhow::Ok(())
+/// ```
+///
+/// The values must cover every table entry, in index order: `residues.count(k)` of them.
+pub struct TableBuilder {
+    k: u32,
+    l: u32,
+    residues: Residues,
+    ver: u32,
+}
+
+impl TableBuilder {
+    /// A ver=2 (u32 entries) table of the odd residues mod 2^k.
+    pub fn new(k: u32, l: u32) -> TableBuilder {
+        TableBuilder { k, l, residues: Residues::Odd, ver: 2 }
+    }
+
+    pub fn residues(mut self, residues: Residues) -> TableBuilder {
+        self.residues = residues;
+        self
+    }
+
+    /// Body format: 1 (u16 entries), 2 (u32 entries) or `VER_RLE`. ver=3 also stores each
+    /// trajectory's end residue, which plain values do not carry.
+    pub fn version(mut self, ver: u32) -> TableBuilder {
+        self.ver = ver;
+        self
+    }
+
+    /// The header this builder writes.
+    pub fn header(&self) -> Header {
+        Header {
+            magic: *b"CALT",
+            ver: self.ver,
+            k: self.k,
+            l: self.l,
+            count: self.residues.count(self.k),
+            flags: self.residues.flag(),
+            algo_ver: ALGO_VER,
+            _reserved: [0u8; 2],
+        }
+    }
+
+    /// Write the table to `w` and return the sha256 of its body (the trailer, which is
+    /// also a manifest's `sha256_table_hex`).
+    pub fn write<W: Write>(&self, w: &mut W, values: impl IntoIterator<Item = u32>) -> anyhow::Result<[u8; 32]> {
+        let h = self.header();
+        check_count(h.k, h.count, self.residues)?;
+        anyhow::ensure!(
+            matches!(h.ver, 1 | 2 | VER_RLE),
+            "TableBuilder: ver={} is not supported (1, 2 or {VER_RLE})", h.ver
+        );
+        let mut hasher = Sha256::new();
+        let mut put = |w: &mut W, bytes: &[u8]| -> std::io::Result<()> {
+            hasher.update(bytes);
+            w.write_all(bytes)
+        };
+        write_header(w, &h)?;
+        let mut n = 0u64;
+        if h.ver == VER_RLE {
+            let values: Vec<u32> = values.into_iter().collect();
+            n = values.len() as u64;
+            anyhow::ensure!(n == h.count, "TableBuilder: {n} values for count={}", h.count);
+            put(w, &rle_encode(&values))?;
+        } else {
+            for v in values {
+                anyhow::ensure!(n < h.count, "TableBuilder: more than count={} values", h.count);
+                if h.ver == 1 {
+                    let v16 = u16::try_from(v).map_err(|_| anyhow::anyhow!("TableBuilder: S={v} at index {n} does not fit ver=1 (u16)"))?;
+                    put(w, &v16.to_le_bytes())?;
+                } else {
+                    put(w, &v.to_le_bytes())?;
+                }
+                n += 1;
+            }
+            anyhow::ensure!(n == h.count, "TableBuilder: {n} values for count={}", h.count);
+        }
+        let digest: [u8; 32] = hasher.finalize().into();
+        w.write_all(&digest)?;
+        Ok(digest)
+    }
+
+    /// [`TableBuilder::write`] to a new file at `path`.
+    pub fn write_file(&self, path: &Path, values: impl IntoIterator<Item = u32>) -> anyhow::Result<[u8; 32]> {
+        let mut w = std::io::BufWriter::new(File::create(path).map_err(path_io_error(path))?);
+        let digest = self.write(&mut w, values)?;
+        w.flush().map_err(path_io_error(path))?;
+        Ok(digest)
+    }
+}
+
+/// S-sum over `l` accelerated steps for the odd residue `(idx<<1)|1` mod 2^k.
+#[inline]
+pub fn s_sum(mask: u64, l: u32, idx: u64) -> u32 {
+    steps(mask, l, (idx << 1) | 1).0
+}
+
+/// Run `l` accelerated steps from the odd residue `m` mod 2^k, returning the S-sum and
+/// the residue reached. Running `a` then `b` steps from there equals running `a + b`.
+#[inline]
+pub fn steps(mask: u64, l: u32, mut m: u64) -> (u32, u64) {
+    let mut s: u64 = 0;
+    for _ in 0..l {
+        let t = 3u64.wrapping_mul(m & mask).wrapping_add(1);
+        let e = t.trailing_zeros() as u64;
+        s += e;
+        m = (t >> e) & mask;
+    }
+    (s.min(u32::MAX as u64) as u32, m)
+}
+
+/// S-sum for an arbitrary residue `r` mod 2^k: the leading halvings of an even residue
+/// count towards S, then `l` accelerated steps run from its odd part. The zero residue is
+/// treated as 2^k (k halvings, continuing from 1).
+#[inline]
+pub fn s_sum_all(mask: u64, l: u32, r: u64) -> u32 {
+    s_sum_all_state(mask, l, r).0
+}
+
+/// `s_sum_all` together with the odd residue reached.
+#[inline]
+pub fn s_sum_all_state(mask: u64, l: u32, r: u64) -> (u32, u64) {
+    let r = r & mask;
+    let (v, m) = if r == 0 {
+        (mask.count_ones(), 1)
+    } else {
+        (r.trailing_zeros(), r >> r.trailing_zeros())
+    };
+    let (s, m) = steps(mask, l, m);
+    ((v as u64 + s as u64).min(u32::MAX as u64) as u32, m)
+}
+
+/// One row of a traced trajectory: residue `m` before the step, exponent `e` of the
+/// halvings taken, running S-sum, and the residue reached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceStep {
+    /// 1-based step number; 0 marks the leading halvings of an even residue
+    pub step: u32,
+    pub m: u64,
+    pub e: u32,
+    pub s: u64,
+    pub next: u64,
+}
+
+/// Fixed points of one odd step mod 2^k, `m -> ((3m+1) >> e) & mask`, with their exponent
+/// `e`. A fixed point with exponent e has `m * (2^e - 3) = 1 mod 2^k`, so there is at most one
+/// candidate per e, and `3m+1 < 2^(k+2)` bounds e; each candidate is then stepped to check
+/// that its exponent really is e. For every k this finds only the trivial cycle `1 -> 1`
+/// (e = 2). The all-ones residue, i.e. the 2-adic fixed point -1, is not one: every step
+/// loses its top bit.
+pub fn fixed_points(k: u32) -> Vec<(u64, u32)> {
+    let mask: u64 = (1u64 << k) - 1;
+    (1..=k + 2)
+        .filter_map(|e| {
+            // inverse of the odd 2^e - 3 mod 2^64 by Newton's iteration, then reduced
+            let a = (1u64 << e).wrapping_sub(3);
+            let mut x = a;
+            for _ in 0..6 {
+                x = x.wrapping_mul(2u64.wrapping_sub(a.wrapping_mul(x)));
+            }
+            let m = x & mask;
+            (steps(mask, 1, m) == (e, m)).then_some((m, e))
+        })
+        .collect()
+}
+
+/// Step-by-step version of `Residues::s_sum` for table entry `idx`; the `e` column sums
+/// to the S value stored in the table (before u32 saturation).
+pub fn trace(residues: Residues, mask: u64, l: u32, idx: u64) -> Vec<TraceStep> {
+    let mut out = Vec::with_capacity(l as usize + 1);
+    let mut s: u64 = 0;
+    let mut m = match residues {
+        Residues::Odd => (idx << 1) | 1,
+        Residues::All => {
+            let r = idx & mask;
+            let (v, odd) = if r == 0 { (mask.count_ones(), 1) } else { (r.trailing_zeros(), r >> r.trailing_zeros()) };
+            if v > 0 {
+                s = v as u64;
+                out.push(TraceStep { step: 0, m: r, e: v, s, next: odd });
+            }
+            odd
+        }
+    };
+    for step in 1..=l {
+        let t = 3u64.wrapping_mul(m & mask).wrapping_add(1);
+        let e = t.trailing_zeros();
+        s += e as u64;
+        let next = (t >> e) & mask;
+        out.push(TraceStep { step, m, e, s, next });
+        m = next;
+    }
+    out
+}
+
+/// Lazily computed `(index, S, eps_i)` for each odd residue mod 2^k, in index order, with
+/// `eps_i = S/l - log2(3)`: the entries of the table `gen` would write, without the table.
+/// Memory is constant; nothing is buffered between items.
+pub fn drift_iter(k: u32, l: u32) -> impl Iterator<Item = (u64, u32, f64)> {
+    let (mask, c) = drift_params(k);
+    (0..Residues::Odd.count(k)).map(move |i| drift_entry(mask, l, c, i))
+}
+
+/// Parallel form of [`drift_iter`], split by index range across the current rayon pool;
+/// memory is that of the consumer's reduction, not of the table.
+pub fn par_drift(k: u32, l: u32) -> impl rayon::iter::ParallelIterator<Item = (u64, u32, f64)> {
+    use rayon::iter::{IntoParallelIterator, ParallelIterator};
+    let (mask, c) = drift_params(k);
+    (0..Residues::Odd.count(k)).into_par_iter().map(move |i| drift_entry(mask, l, c, i))
+}
+
+fn drift_params(k: u32) -> (u64, f64) {
+    assert!((1..=63).contains(&k), "k={k} out of range");
+    ((1u64 << k) - 1, log2_3())
+}
+
+#[inline]
+fn drift_entry(mask: u64, l: u32, c: f64, i: u64) -> (u64, u32, f64) {
+    let s = s_sum(mask, l, i);
+    (i, s, drift_eps(s, l, c))
+}
+
+/// A table for a given k covers exactly the 2^(k-1) odd residues mod 2^k
+/// (or all 2^k residues when FLAG_ALL_RESIDUES is set).
+pub fn check_count(k: u32, count: u64, residues: Residues) -> anyhow::Result<()> {
+    anyhow::ensure!((1..=62).contains(&k), "bad header: k={k} out of range");
+    let expected = residues.count(k);
+    let form = match residues { Residues::Odd => "2^(k-1)", Residues::All => "2^k" };
+    anyhow::ensure!(
+        count == expected,
+        "bad header: count={count} but k={k} requires count={form}={expected}"
+    );
+    Ok(())
+}
+
+/// Encode `values` as a ver=4 body of `(s, run)` pairs.
+pub fn rle_encode(values: &[u32]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for run in values.chunk_by(|a, b| a == b) {
+        out.extend_from_slice(&run[0].to_le_bytes());
+        out.extend_from_slice(&(run.len() as u32).to_le_bytes());
+    }
+    out
+}
+
+/// Expand a ver=4 body back to `count` values, rejecting empty runs and runs that do not
+/// add up to `count`.
+pub fn rle_decode(body: &[u8], count: u64) -> anyhow::Result<Vec<u32>> {
+    anyhow::ensure!(body.len().is_multiple_of(8), "bad file length");
+    let mut values = Vec::with_capacity(count as usize);
+    for pair in body.chunks_exact(8) {
+        let s = u32::from_le_bytes(pair[..4].try_into()?);
+        let run = u32::from_le_bytes(pair[4..].try_into()?);
+        anyhow::ensure!(run > 0, "bad rle body: empty run at entry {}", values.len());
+        anyhow::ensure!(values.len() as u64 + run as u64 <= count, "bad rle body: runs exceed count={count}");
+        values.resize(values.len() + run as usize, s);
+    }
+    anyhow::ensure!(values.len() as u64 == count, "bad rle body: runs cover {} of count={count}", values.len());
+    Ok(values)
+}
+
+/// The table path `-`: read standard input instead of a file.
+pub fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Prefix an IO error with the path it concerns. The result is still an `io::Error`, so
+/// callers can tell IO failures apart (e.g. for `--error-format json`).
+pub fn path_io_error(path: &Path) -> impl FnOnce(std::io::Error) -> std::io::Error + '_ {
+    move |e| std::io::Error::new(e.kind(), format!("{}: {e}", path.display()))
+}
+
+fn open_input(path: &Path) -> anyhow::Result<Box<dyn Read + Send>> {
+    if is_stdin(path) {
+        return Ok(Box::new(std::io::stdin()));
+    }
+    Ok(Box::new(File::open(path).map_err(path_io_error(path))?))
+}
+
+/// Read a whole table file (`-` for stdin), transparently decompressing gzip (`1f 8b` magic)
+/// so `.bin.gz` is accepted wherever a table is; hashes cover the decompressed bytes.
+pub fn read_table_file(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let mut raw = Vec::new();
+    open_input(path)?.read_to_end(&mut raw)?;
+    gunzip_if_needed(path, raw)
+}
+
+/// `read_table_file` bypassing the page cache where the platform allows it (O_DIRECT on
+/// Linux, F_NOCACHE on macOS), so streaming a huge table does not evict everything else.
+/// Falls back to a buffered read otherwise; the flag tells which one happened.
+pub fn read_table_file_direct(path: &Path) -> anyhow::Result<(Vec<u8>, bool)> {
+    if !is_stdin(path) {
+        if let Some(raw) = direct::read(path).map_err(path_io_error(path))? {
+            return Ok((gunzip_if_needed(path, raw)?, true));
+        }
+    }
+    Ok((read_table_file(path)?, false))
+}
+
+/// The regular file at `path`, opened with a sequential-access hint
+/// (`posix_fadvise(POSIX_FADV_SEQUENTIAL)`) so the OS reads ahead more aggressively.
+/// `None` for stdin; the flag tells whether the hint was accepted.
+fn open_sequential(path: &Path) -> anyhow::Result<Option<(File, bool)>> {
+    if is_stdin(path) {
+        return Ok(None);
+    }
+    let file = File::open(path).map_err(path_io_error(path))?;
+    let applied = prefetch::advise_sequential(&file);
+    Ok(Some((file, applied)))
+}
+
+/// `read_table_file` with a sequential read-ahead hint on the file where the platform
+/// supports it; a pure IO optimisation. The flag tells whether the hint was applied.
+pub fn read_table_file_prefetch(path: &Path) -> anyhow::Result<(Vec<u8>, bool)> {
+    match open_sequential(path)? {
+        Some((mut file, applied)) => {
+            let mut raw = Vec::new();
+            file.read_to_end(&mut raw).map_err(path_io_error(path))?;
+            Ok((gunzip_if_needed(path, raw)?, applied))
+        }
+        None => Ok((read_table_file(path)?, false)),
+    }
+}
+
+fn gunzip_if_needed(path: &Path, raw: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    if raw.starts_with(&[0x1f, 0x8b]) {
+        let mut data = Vec::new();
+        flate2::read::MultiGzDecoder::new(&raw[..]).read_to_end(&mut data)
+            .map_err(|e| anyhow::anyhow!("{}: gzip: {e}", path.display()))?;
+        return Ok(data);
+    }
+    Ok(raw)
+}
+
+/// Load and fully validate a table (header, length, sha256 trailer) into memory.
+pub fn read_table_bytes(path: &Path) -> anyhow::Result<(Header, Vec<u32>)> {
+    let reader = TableReader::open(path)?;
+    let header = *reader.header();
+    let table = reader.map(|e| e.map(|(_, s)| s)).collect::<anyhow::Result<Vec<u32>>>()?;
+    Ok((header, table))
+}
+
+enum Source {
+    Stream(Box<dyn Read + Send>),
+    Mapped { data: mmap::Mapped, pos: usize },
+}
+
+/// Streams `(index, s)` pairs from a table file without loading it whole.
+///
+/// The header is validated on open; the sha256 trailer and file length are checked once
+/// the last entry has been read, so a corrupt table ends the iteration with an `Err` item
+/// rather than `None`. The iterator yields nothing after its first error.
+pub struct TableReader {
+    header: Header,
+    src: Source,
+    hasher: Sha256,
+    next: u64,
+    done: bool,
+    check_hash: bool,
+    /// ver=4: value and remaining length of the current run
+    run: (u32, u32),
+    /// The trailer, once `finish` has matched it against the body
+    sha256: Option<[u8; 32]>,
+}
+
+impl TableReader {
+    /// Buffered reads (`-` for stdin); gzip-compressed tables are decompressed on the fly.
+    pub fn open(path: &Path) -> anyhow::Result<TableReader> {
+        TableReader::from_input(path, open_input(path)?)
+    }
+
+    /// [`TableReader::open`] with a sequential read-ahead hint on the file (see
+    /// [`read_table_file_prefetch`]); the flag tells whether the hint was applied.
+    pub fn open_prefetch(path: &Path) -> anyhow::Result<(TableReader, bool)> {
+        match open_sequential(path)? {
+            Some((file, applied)) => Ok((TableReader::from_input(path, Box::new(file))?, applied)),
+            None => Ok((TableReader::open(path)?, false)),
+        }
+    }
+
+    fn from_input(path: &Path, input: Box<dyn Read + Send>) -> anyhow::Result<TableReader> {
+        let mut buf = BufReader::new(input);
+        let mut r: Box<dyn Read + Send> = if buf.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+            Box::new(BufReader::new(flate2::read::MultiGzDecoder::new(buf)))
+        } else {
+            Box::new(buf)
+        };
+        let mut head = [0u8; 32];
+        r.read_exact(&mut head).map_err(|e| match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => anyhow::anyhow!("file too small"),
+            _ => path_io_error(path)(e).into(),
+        })?;
+        Ok(TableReader::new(Header::decode(&head)?, Source::Stream(r)))
+    }
+
+    /// Memory-mapped reads of an uncompressed table (a plain read into memory where
+    /// mmap is unavailable). Stdin (`-`), pipes and other non-regular files cannot be
+    /// mapped and fall back to [`TableReader::open`].
+    ///
+    /// # Safety
+    /// The file must not be truncated or modified while the reader is alive.
+    pub unsafe fn open_mmap(path: &Path) -> anyhow::Result<TableReader> {
+        if is_stdin(path) {
+            return TableReader::open(path);
+        }
+        let file = File::open(path).map_err(path_io_error(path))?;
+        if !file.metadata()?.is_file() {
+            return TableReader::open(path);
+        }
+        let data = mmap::map(&file).map_err(|e| anyhow::anyhow!("{}: mmap: {e}", path.display()))?;
+        anyhow::ensure!(!data.starts_with(&[0x1f, 0x8b]), "{}: gzip tables cannot be mapped", path.display());
+        let header = Header::parse(&data)?;
+        if let Some(body) = header.body_len() {
+            anyhow::ensure!(data.len() == 32 + body + 32, "bad file length");
+        }
+        Ok(TableReader::new(header, Source::Mapped { data, pos: 32 }))
+    }
+
+    fn new(header: Header, src: Source) -> TableReader {
+        TableReader { header, src, hasher: Sha256::new(), next: 0, done: false, check_hash: true, run: (0, 0), sha256: None }
+    }
+
+    /// Skip the sha256 trailer and length checks: the iteration simply ends after the
+    /// last entry. For quick looks at files whose integrity is checked elsewhere.
+    pub fn without_hash_check(mut self) -> TableReader {
+        self.check_hash = false;
+        self
+    }
+
+    pub fn header(&self) -> &Header { &self.header }
+
+    /// The sha256 trailer, available once iteration has ended without error (and the
+    /// hash was checked); `None` before that.
+    pub fn sha256(&self) -> Option<&[u8; 32]> { self.sha256.as_ref() }
+
+    fn read_bytes(&mut self, out: &mut [u8]) -> anyhow::Result<()> {
+        match &mut self.src {
+            Source::Stream(r) => r.read_exact(out).map_err(|e| match e.kind() {
+                std::io::ErrorKind::UnexpectedEof => anyhow::anyhow!("bad file length"),
+                _ => e.into(),
+            }),
+            Source::Mapped { data, pos } => {
+                let src = data.get(*pos..*pos + out.len()).ok_or_else(|| anyhow::anyhow!("bad file length"))?;
+                out.copy_from_slice(src);
+                *pos += out.len();
+                Ok(())
+            }
+        }
+    }
+
+    fn read_entry(&mut self) -> anyhow::Result<(u32, Option<u32>)> {
+        if self.header.ver == VER_RLE {
+            if self.run.1 == 0 {
+                let mut b = [0u8; 8];
+                self.read_bytes(&mut b)?;
+                if self.check_hash {
+                    self.hasher.update(b);
+                }
+                let run = u32::from_le_bytes(b[4..].try_into()?);
+                anyhow::ensure!(run > 0, "bad rle body: empty run at entry {}", self.next);
+                anyhow::ensure!(self.next + run as u64 <= self.header.count, "bad rle body: runs exceed count={}", self.header.count);
+                self.run = (u32::from_le_bytes(b[..4].try_into()?), run);
+            }
+            self.run.1 -= 1;
+            return Ok((self.run.0, None));
+        }
+        let width = self.header.width();
+        let mut b = [0u8; 8];
+        self.read_bytes(&mut b[..width])?;
+        if self.check_hash {
+            self.hasher.update(&b[..width]);
+        }
+        let s = u32::from_le_bytes(b[..4].try_into()?);
+        Ok((s, (self.header.ver == VER_STATE).then(|| u32::from_le_bytes(b[4..].try_into().unwrap()))))
+    }
+
+    /// Check the trailer against the running hash and that nothing follows it.
+    fn finish(&mut self) -> anyhow::Result<()> {
+        let mut trailer = [0u8; 32];
+        self.read_bytes(&mut trailer)?;
+        let digest = std::mem::take(&mut self.hasher).finalize();
+        anyhow::ensure!(trailer == digest.as_slice(), "table sha256 mismatch");
+        match &mut self.src {
+            Source::Stream(r) => anyhow::ensure!(r.read(&mut [0u8; 1])? == 0, "bad file length"),
+            Source::Mapped { data, pos } => anyhow::ensure!(*pos == data.len(), "bad file length"),
+        }
+        self.sha256 = Some(trailer);
+        Ok(())
+    }
+}
+
+impl TableReader {
+    /// Like `next`, but also returns the stored end residue `m` of ver=3 tables.
+    pub fn next_entry(&mut self) -> Option<anyhow::Result<(u64, u32, Option<u32>)>> {
+        if self.done {
+            return None;
+        }
+        if self.next == self.header.count {
+            self.done = true;
+            if !self.check_hash {
+                return None;
+            }
+            return self.finish().err().map(Err);
+        }
+        match self.read_entry() {
+            Ok((s, m)) => {
+                self.next += 1;
+                Some(Ok((self.next - 1, s, m)))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl Iterator for TableReader {
+    type Item = anyhow::Result<(u64, u32)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_entry().map(|e| e.map(|(i, s, _)| (i, s)))
+    }
+}
+
+/// Bundle ("CALB") format version: several table files, each with an optional manifest,
+/// behind a directory so a single (k, l) entry can be read without touching the rest.
+///
+/// ```text
+/// header     16 bytes   magic "CALB", ver: u32, entries: u32, reserved: u32 (0)
+/// directory  entries x 104 bytes, see `BundleEntry`
+/// dir sha256 32 bytes   over the header and directory
+/// payload    each entry's table file, then its manifest JSON, in directory order
+/// ```
+pub const BUNDLE_VER: u32 = 1;
+pub const BUNDLE_HEADER_LEN: usize = 16;
+pub const BUNDLE_ENTRY_LEN: usize = 104;
+
+/// One directory entry of a bundle. Offsets are absolute; `manifest_len` is 0 (and the
+/// manifest fields zero) when the entry has no manifest.
+///
+/// Layout: k: u32, l: u32, table_offset: u64, table_len: u64, manifest_offset: u64,
+/// manifest_len: u64, table_sha256: [u8; 32], manifest_sha256: [u8; 32].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BundleEntry {
+    pub k: u32,
+    pub l: u32,
+    pub table_offset: u64,
+    pub table_len: u64,
+    pub manifest_offset: u64,
+    pub manifest_len: u64,
+    /// sha256 of the stored table file (header, body and trailer)
+    pub table_sha256: [u8; 32],
+    pub manifest_sha256: [u8; 32],
+}
+
+impl BundleEntry {
+    fn encode(&self) -> [u8; BUNDLE_ENTRY_LEN] {
+        let mut b = [0u8; BUNDLE_ENTRY_LEN];
+        b[0..4].copy_from_slice(&self.k.to_le_bytes());
+        b[4..8].copy_from_slice(&self.l.to_le_bytes());
+        b[8..16].copy_from_slice(&self.table_offset.to_le_bytes());
+        b[16..24].copy_from_slice(&self.table_len.to_le_bytes());
+        b[24..32].copy_from_slice(&self.manifest_offset.to_le_bytes());
+        b[32..40].copy_from_slice(&self.manifest_len.to_le_bytes());
+        b[40..72].copy_from_slice(&self.table_sha256);
+        b[72..104].copy_from_slice(&self.manifest_sha256);
+        b
+    }
+
+    fn decode(b: &[u8]) -> BundleEntry {
+        let u32_at = |i: usize| u32::from_le_bytes(b[i..i + 4].try_into().unwrap());
+        let u64_at = |i: usize| u64::from_le_bytes(b[i..i + 8].try_into().unwrap());
+        BundleEntry {
+            k: u32_at(0),
+            l: u32_at(4),
+            table_offset: u64_at(8),
+            table_len: u64_at(16),
+            manifest_offset: u64_at(24),
+            manifest_len: u64_at(32),
+            table_sha256: b[40..72].try_into().unwrap(),
+            manifest_sha256: b[72..104].try_into().unwrap(),
+        }
+    }
+}
+
+/// A file to store in a bundle: a table and, optionally, its manifest.
+pub struct BundleInput<'a> {
+    pub k: u32,
+    pub l: u32,
+    pub table: &'a Path,
+    pub manifest: Option<&'a Path>,
+}
+
+fn sha256_len(path: &Path) -> anyhow::Result<([u8; 32], u64)> {
+    let mut r = BufReader::new(File::open(path).map_err(path_io_error(path))?);
+    let mut hasher = Sha256::new();
+    let len = std::io::copy(&mut r, &mut hasher).map_err(path_io_error(path))?;
+    Ok((hasher.finalize().into(), len))
+}
+
+/// Write a bundle of `inputs` to `out`, in the given order. Every file is hashed first so the
+/// directory can lead the payload; the copies are then checked against those lengths.
+pub fn write_bundle(out: &Path, inputs: &[BundleInput]) -> anyhow::Result<Vec<BundleEntry>> {
+    let mut offset = (BUNDLE_HEADER_LEN + BUNDLE_ENTRY_LEN * inputs.len() + 32) as u64;
+    let mut entries = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let (table_sha256, table_len) = sha256_len(input.table)?;
+        let (manifest_sha256, manifest_len) = match input.manifest {
+            Some(m) => sha256_len(m)?,
+            None => ([0u8; 32], 0),
+        };
+        entries.push(BundleEntry {
+            k: input.k,
+            l: input.l,
+            table_offset: offset,
+            table_len,
+            manifest_offset: if manifest_len > 0 { offset + table_len } else { 0 },
+            manifest_len,
+            table_sha256,
+            manifest_sha256,
+        });
+        offset += table_len + manifest_len;
+    }
+    let mut head = Vec::with_capacity(BUNDLE_HEADER_LEN + BUNDLE_ENTRY_LEN * entries.len());
+    head.extend_from_slice(b"CALB");
+    head.extend_from_slice(&BUNDLE_VER.to_le_bytes());
+    head.extend_from_slice(&u32::try_from(entries.len())?.to_le_bytes());
+    head.extend_from_slice(&0u32.to_le_bytes());
+    for e in &entries {
+        head.extend_from_slice(&e.encode());
+    }
+    let mut w = std::io::BufWriter::new(File::create(out).map_err(path_io_error(out))?);
+    w.write_all(&head)?;
+    w.write_all(&Sha256::digest(&head))?;
+    for (input, e) in inputs.iter().zip(&entries) {
+        for (path, len) in [(Some(input.table), e.table_len), (input.manifest, e.manifest_len)] {
+            let Some(path) = path else { continue };
+            let copied = std::io::copy(&mut File::open(path).map_err(path_io_error(path))?, &mut w)?;
+            anyhow::ensure!(copied == len, "{}: changed while bundling", path.display());
+        }
+    }
+    w.flush()?;
+    Ok(entries)
+}
+
+/// A bundle opened for random access: the header and directory are read and checked on
+/// open, each entry's bytes only when asked for.
+pub struct Bundle {
+    file: File,
+    pub entries: Vec<BundleEntry>,
+}
+
+impl Bundle {
+    pub fn open(path: &Path) -> anyhow::Result<Bundle> {
+        let mut file = File::open(path).map_err(path_io_error(path))?;
+        let file_len = file.metadata()?.len();
+        let mut head = [0u8; BUNDLE_HEADER_LEN];
+        file.read_exact(&mut head).map_err(|_| anyhow::anyhow!("{}: bundle too small", path.display()))?;
+        anyhow::ensure!(&head[0..4] == b"CALB", "{}: not a bundle (bad magic)", path.display());
+        let ver = u32::from_le_bytes(head[4..8].try_into()?);
+        anyhow::ensure!(ver == BUNDLE_VER, "{}: unsupported bundle ver={ver}", path.display());
+        let n = u32::from_le_bytes(head[8..12].try_into()?) as u64;
+        let data_start = BUNDLE_HEADER_LEN as u64 + BUNDLE_ENTRY_LEN as u64 * n + 32;
+        anyhow::ensure!(data_start <= file_len, "{}: directory of {n} entries runs past the end", path.display());
+        let mut dir = vec![0u8; BUNDLE_ENTRY_LEN * n as usize];
+        file.read_exact(&mut dir)?;
+        let mut digest = [0u8; 32];
+        file.read_exact(&mut digest)?;
+        let mut hasher = Sha256::new();
+        hasher.update(head);
+        hasher.update(&dir);
+        anyhow::ensure!(hasher.finalize().as_slice() == digest, "{}: bundle directory sha256 mismatch", path.display());
+        let entries: Vec<BundleEntry> = dir.chunks_exact(BUNDLE_ENTRY_LEN).map(BundleEntry::decode).collect();
+        for e in &entries {
+            for (offset, len) in [(e.table_offset, e.table_len), (e.manifest_offset, e.manifest_len)] {
+                anyhow::ensure!(
+                    len == 0 || (offset >= data_start && offset.checked_add(len).is_some_and(|end| end <= file_len)),
+                    "{}: entry k={} l={} points outside the payload", path.display(), e.k, e.l
+                );
+            }
+        }
+        Ok(Bundle { file, entries })
+    }
+
+    pub fn find(&self, k: u32, l: u32) -> Option<&BundleEntry> {
+        self.entries.iter().find(|e| (e.k, e.l) == (k, l))
+    }
+
+    fn read_checked(&mut self, offset: u64, len: u64, sha256: &[u8; 32]) -> anyhow::Result<Vec<u8>> {
+        use std::io::{Seek, SeekFrom};
+        self.file.seek(SeekFrom::Start(offset))?;
+        let mut data = vec![0u8; len as usize];
+        self.file.read_exact(&mut data)?;
+        anyhow::ensure!(Sha256::digest(&data).as_slice() == sha256, "sha256 mismatch");
+        Ok(data)
+    }
+
+    /// The entry's table file, checked against its directory sha256.
+    pub fn read_table(&mut self, e: &BundleEntry) -> anyhow::Result<Vec<u8>> {
+        self.read_checked(e.table_offset, e.table_len, &e.table_sha256)
+            .map_err(|err| anyhow::anyhow!("bundle entry k={} l={}: table {err}", e.k, e.l))
+    }
+
+    /// The entry's manifest JSON, if it has one, checked against its directory sha256.
+    pub fn read_manifest(&mut self, e: &BundleEntry) -> anyhow::Result<Option<Vec<u8>>> {
+        if e.manifest_len == 0 {
+            return Ok(None);
+        }
+        self.read_checked(e.manifest_offset, e.manifest_len, &e.manifest_sha256)
+            .map(Some)
+            .map_err(|err| anyhow::anyhow!("bundle entry k={} l={}: manifest {err}", e.k, e.l))
+    }
+}
+
+mod direct {
+    use std::path::Path;
+
+    /// Whole-file read with caching disabled, or None when this platform or filesystem
+    /// does not support it.
+    #[cfg(target_os = "linux")]
+    pub fn read(path: &Path) -> std::io::Result<Option<Vec<u8>>> {
+        use std::io::Read;
+        use std::os::unix::fs::OpenOptionsExt;
+        // O_DIRECT transfers need block-aligned buffers; 4 KiB covers common devices
+        const ALIGN: usize = 4096;
+        const BUF: usize = 1 << 20;
+        let mut f = match std::fs::OpenOptions::new().read(true).custom_flags(libc::O_DIRECT).open(path) {
+            Ok(f) => f,
+            Err(e) if e.raw_os_error() == Some(libc::EINVAL) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let layout = std::alloc::Layout::from_size_align(BUF, ALIGN).expect("valid layout");
+        let ptr = unsafe { std::alloc::alloc(layout) };
+        assert!(!ptr.is_null(), "aligned buffer allocation failed");
+        struct Aligned(*mut u8, std::alloc::Layout);
+        impl Drop for Aligned {
+            fn drop(&mut self) { unsafe { std::alloc::dealloc(self.0, self.1) } }
+        }
+        let buf = Aligned(ptr, layout);
+        let chunk = unsafe { std::slice::from_raw_parts_mut(buf.0, BUF) };
+        let mut out = Vec::with_capacity(f.metadata()?.len() as usize);
+        loop {
+            match f.read(chunk) {
+                Ok(0) => return Ok(Some(out)),
+                Ok(n) => out.extend_from_slice(&chunk[..n]),
+                // some filesystems accept the flag on open but reject direct reads
+                Err(e) if e.raw_os_error() == Some(libc::EINVAL) && out.is_empty() => return Ok(None),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    pub fn read(path: &Path) -> std::io::Result<Option<Vec<u8>>> {
+        use std::io::Read;
+        use std::os::unix::io::AsRawFd;
+        let mut f = std::fs::File::open(path)?;
+        if unsafe { libc::fcntl(f.as_raw_fd(), libc::F_NOCACHE, 1) } == -1 {
+            return Ok(None);
+        }
+        let mut out = Vec::new();
+        f.read_to_end(&mut out)?;
+        Ok(Some(out))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    pub fn read(_path: &Path) -> std::io::Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+}
+
+mod prefetch {
+    use std::fs::File;
+
+    /// Advise sequential access for the whole file; false where unsupported or refused.
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+    pub fn advise_sequential(file: &File) -> bool {
+        use std::os::unix::io::AsRawFd;
+        unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_SEQUENTIAL) == 0 }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+    pub fn advise_sequential(_file: &File) -> bool {
+        false
+    }
+}
+
+#[cfg(unix)]
+mod mmap {
+    use std::fs::File;
+    use std::os::unix::io::AsRawFd;
+
+    /// Read-only private mapping of a whole file, unmapped on drop.
+    pub struct Mapped {
+        ptr: *mut libc::c_void,
+        len: usize,
+    }
+
+    // The mapping is read-only and owned by this value.
+    unsafe impl Send for Mapped {}
+
+    pub unsafe fn map(file: &File) -> std::io::Result<Mapped> {
+        let len = file.metadata()?.len() as usize;
+        if len == 0 {
+            return Ok(Mapped { ptr: std::ptr::null_mut(), len });
+        }
+        let ptr = libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ, libc::MAP_PRIVATE, file.as_raw_fd(), 0);
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Mapped { ptr, len })
+    }
+
+    impl std::ops::Deref for Mapped {
+        type Target = [u8];
+        fn deref(&self) -> &[u8] {
+            if self.len == 0 {
+                return &[];
+            }
+            unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+        }
+    }
+
+    impl Drop for Mapped {
+        fn drop(&mut self) {
+            if self.len != 0 {
+                unsafe { libc::munmap(self.ptr, self.len) };
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod mmap {
+    use std::io::Read;
+
+    pub type Mapped = Vec<u8>;
+
+    pub unsafe fn map(mut file: &std::fs::File) -> std::io::Result<Mapped> {
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        Ok(data)
+    }
+}
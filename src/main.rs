@@ -1,9 +1,14 @@
 use clap::{Parser, Subcommand};
+use collatz_cert::{
+    drift_eps, fixed_points, is_stdin, log2_3, log2_3_digits, manifest_schema, manifest_sha256, neg_drift_bound, path_io_error, read_table_bytes, read_table_file,
+    read_table_file_direct, read_table_file_prefetch, rle_decode, rle_encode, steps, trace, write_header,
+    Header, Manifest, Residues, TableBuilder, TableReader, ALGO_VER, FLAG_PARTIAL, VER_RLE, VER_STATE,
+};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::fs::File;
-use std::io::{Read, Write, BufReader};
+use std::io::{Read, Write, Seek, BufReader};
 use std::path::{Path, PathBuf};
 use std::cmp::{min, max};
 
@@ -11,6 +16,17 @@ use std::cmp::{min, max};
 #[derive(Parser)]
 #[command(author, version, about="Collatz drift certificate")]
 struct Args {
+    /// Config file (flat TOML) with defaults for threads, output_dir, hash, threshold_mode.
+    /// Precedence: command-line flag > config file > built-in default
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// Disable colored output (also honours NO_COLOR and non-TTY stderr)
+    #[arg(long, global = true, default_value_t = false)]
+    no_color: bool,
+    /// How a failing command reports its error: text on stderr, or one JSON object
+    /// {"error", "kind"} on stdout
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Text)]
+    error_format: ErrorFormat,
     #[command(subcommand)]
     cmd: Cmd,
 }
@@ -18,438 +34,3590 @@ struct Args {
 #[derive(Subcommand)]
 enum Cmd {
     Gen {
-        #[arg(long, default_value_t = 24)] k: u32,
-        #[arg(long, default_value_t = 256)] l: u32,
-        #[arg(long, default_value_t = 0)] threads: usize,
+        /// [default: $COLLATZ_K, else 24]
+        #[arg(long)] k: Option<u32>,
+        /// [default: $COLLATZ_L, else 256]
+        #[arg(long)] l: Option<u32>,
+        /// Worker threads; 0 = all cores [default: 0]
+        #[arg(long)] threads: Option<usize>,
         /// Optional output table path; defaults to table_k{K}_l{L}_v2.bin
-        #[arg(long)] out_table: Option<PathBuf>,
+        #[arg(long, conflicts_with = "table_stdout")] out_table: Option<PathBuf>,
         /// Optional output manifest path; defaults to cert_k{K}_l{L}_v2.json
         #[arg(long)] out_manifest: Option<PathBuf>,
+        /// Sidecar file recording progress; an interrupted run with the same parameters resumes from it
+        #[arg(long)] checkpoint: Option<PathBuf>,
+        /// Cover all 2^K residues (even ones included) instead of the 2^(K-1) odd residues
+        #[arg(long, default_value_t = false)] include_even: bool,
+        /// Also write a Prometheus/OpenMetrics textfile with the certificate gauges
+        #[arg(long)] metrics: Option<PathBuf>,
+        /// Print the manifest JSON to stdout; no manifest file is written unless --out-manifest is given
+        #[arg(long, default_value_t = false)] manifest_stdout: bool,
+        /// Stream the table (header+body+trailer) to stdout instead of writing a file
+        #[arg(long, default_value_t = false, conflicts_with_all = ["manifest_stdout", "checkpoint"])]
+        table_stdout: bool,
+        /// Drift constant c for threshold floor(L*c)+1 and eps; recorded in the manifest [default: log2(3)]
+        #[arg(long, value_parser = parse_log_constant, allow_hyphen_values = true)] log_constant: Option<f64>,
+        /// Free-text note stored in the manifest (`notes`); not part of any check
+        #[arg(long)] note: Option<String>,
+        /// Run a full verify of the written table and manifest before exiting; fails gen if it fails
+        #[arg(long, default_value_t = false, conflicts_with = "table_stdout")] verify_after: bool,
+        /// Exit non-zero if the certificate has pass=false (the files are still written)
+        #[arg(long, default_value_t = false)] require_pass: bool,
+        /// Cap throughput at about N entries per second by pausing between chunks (off by default)
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))] throttle_eps: Option<u64>,
+        /// Print a wall-time breakdown (compute, hash, file IO) at the end
+        #[arg(long, default_value_t = false)] profile: bool,
+        /// Also write the --profile breakdown as JSON to this file (implies --profile)
+        #[arg(long, value_name = "PATH")] profile_json: Option<PathBuf>,
+        /// Write ver=3 entries carrying each residue's end state, so the table can later be extended
+        #[arg(long, default_value_t = false)] with_state: bool,
+        /// Continue a ver=3 table with the same K to the larger --l instead of starting over (implies --with-state)
+        #[arg(long, value_name = "TABLE")] extend_from: Option<PathBuf>,
+        /// Write the step-by-step trajectory of the residue attaining min_S to this CSV file
+        #[arg(long, value_name = "PATH")] dump_worst: Option<PathBuf>,
+        /// Write the sorted indices of all residues with S below the threshold to this file, one per line
+        #[arg(long, value_name = "PATH", conflicts_with = "checkpoint")] dump_failing: Option<PathBuf>,
+        /// Keep at most N indices (the lowest) in --dump-failing
+        #[arg(long, value_name = "N", default_value_t = 1_000_000, requires = "dump_failing")] max_failing: usize,
+        /// Append newline-delimited JSON events (start, progress, complete/error) to this file
+        #[arg(long, value_name = "PATH")] events_json: Option<PathBuf>,
+        /// Hash and write each finished chunk on a dedicated thread while the next one is computed
+        #[arg(long, default_value_t = false, conflicts_with_all = ["checkpoint", "profile", "profile_json"])] pipeline: bool,
+        /// Print one tab-separated line to stdout: k l count min_s threshold pass eps elapsed_ms table_sha8
+        #[arg(long, default_value_t = false, conflicts_with_all = ["table_stdout", "manifest_stdout"])] summary_line: bool,
+        /// Only compute the first N residues and write a table flagged partial (for testing large-K layouts)
+        #[arg(long, value_name = "N", visible_alias = "entries", value_parser = clap::value_parser!(u64).range(1..),
+              conflicts_with_all = ["checkpoint", "extend_from", "metrics", "require_pass", "summary_line", "verify_after"])]
+        max_index: Option<u64>,
     },
     Verify {
-        #[arg(long)] k: u32,
-        #[arg(long)] l: u32,
-        #[arg(long)] table: PathBuf,
+        /// Expected K; read from the table header when omitted
+        #[arg(long)] k: Option<u32>,
+        /// Expected L; read from the table header when omitted
+        #[arg(long)] l: Option<u32>,
+        /// Table file; `-` reads it from stdin
+        #[arg(long, required_unless_present = "no_table")] table: Option<PathBuf>,
+        /// Manifest to check the table against; optional with --expect-min-s
+        #[arg(long, required_unless_present_any = ["structural_only", "expect_min_s"])] manifest: Option<PathBuf>,
+        /// Worker threads; 0 = all cores [default: 0]
+        #[arg(long)] threads: Option<usize>,
+        /// Only check magic, version, length and the sha256 trailer; ignores the manifest
+        #[arg(long, default_value_t = false)] structural_only: bool,
+        /// Only recompute residues in [A,B) (spot check); the SHA trailer is still checked in full
+        #[arg(long, value_name = "A:B", value_parser = parse_index_range)] index_range: Option<(u64, u64)>,
+        /// Only recompute N uniformly random residues (seeded by --seed); the SHA trailer is still checked in full
+        #[arg(long, value_name = "N", conflicts_with = "index_range")] sample: Option<u64>,
+        /// Seed for --sample
+        #[arg(long, default_value_t = 0, requires = "sample")] seed: u64,
+        /// On sha256 mismatch, locate the first corrupt block by comparing against a recompute
+        #[arg(long, default_value_t = false)] diagnose: bool,
+        /// Also write a Prometheus/OpenMetrics textfile with the certificate gauges (full verify only)
+        #[arg(long)] metrics: Option<PathBuf>,
+        /// Stop recomputing at the first mismatch and report it
+        #[arg(long, default_value_t = false)] first_fail: bool,
+        /// After verifying, diff every field against a second manifest; fails if a correctness field differs
+        #[arg(long, value_name = "OTHER", requires = "manifest")] compare_manifest: Option<PathBuf>,
+        /// Append newline-delimited JSON events (start, progress, complete/error) to this file
+        #[arg(long, value_name = "PATH")] events_json: Option<PathBuf>,
+        /// Read the table with direct (page-cache bypassing) IO where supported
+        #[arg(long, default_value_t = false)] direct: bool,
+        /// Hint the OS to read the table ahead sequentially (posix_fadvise) where supported
+        #[arg(long, default_value_t = false, conflicts_with = "direct")] prefetch: bool,
+        /// Fail unless min_S - threshold >= M (the margin is always reported)
+        #[arg(long, value_name = "M", allow_hyphen_values = true, conflicts_with_all = ["index_range", "sample", "structural_only"])] min_margin: Option<i64>,
+        /// After a successful full verify, write a fresh manifest from the recomputed values
+        #[arg(long, value_name = "PATH",
+              conflicts_with_all = ["index_range", "sample", "structural_only", "no_table", "compare_manifest"])]
+        rewrite_manifest: Option<PathBuf>,
+        /// Also compare this binary's build_git_rev/build_rustc (and exec sha256) with the manifest's; warns on mismatch
+        #[arg(long, default_value_t = false, requires = "manifest")] verify_build: bool,
+        /// Narrate the reasoning behind the verdict: threshold formula, min_S, comparison and eps
+        #[arg(long, default_value_t = false, conflicts_with_all = ["index_range", "sample"])] explain: bool,
+        /// Largest accepted |manifest eps - recomputed eps| (a different libm may differ in the last ULP)
+        #[arg(long, default_value_t = EPS_TOLERANCE, value_parser = parse_tolerance)] eps_tolerance: f64,
+        /// Require the manifest eps to equal the recomputed one bit for bit (no float slack)
+        #[arg(long, default_value_t = false, conflicts_with = "eps_tolerance")] exact_eps: bool,
+        /// Run the recompute N times over the loaded table; fails unless every run agrees
+        #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..), conflicts_with = "first_fail")]
+        repeat: u64,
+        /// Print one tab-separated line to stdout after a full verify (same columns as gen --summary-line)
+        #[arg(long, default_value_t = false, conflicts_with_all = ["index_range", "sample"], requires = "manifest")] summary_line: bool,
+        /// Require the recomputed min_S to equal N (full recompute; with a manifest, it must agree too)
+        #[arg(long, value_name = "N", conflicts_with_all = ["index_range", "sample", "structural_only"])] expect_min_s: Option<u32>,
+        /// Threads for the read stage (body decode alongside the sha256), at most 2; 0 = all cores [default: --threads]
+        #[arg(long, value_name = "N")] io_threads: Option<usize>,
+        /// Threads for the recompute stage; 0 = all cores [default: --threads]
+        #[arg(long, value_name = "N")] recompute_threads: Option<usize>,
+        /// No table file: recompute min_S from the manifest's K/L and check min_s/threshold/pass/eps
+        #[arg(long, default_value_t = false, requires = "manifest",
+              conflicts_with_all = ["table", "structural_only", "index_range", "sample", "diagnose", "direct", "prefetch", "first_fail", "repeat",
+                                    "k", "l", "expect_min_s", "explain", "summary_line", "metrics", "compare_manifest", "verify_build",
+                                    "events_json", "io_threads"])]
+        no_table: bool,
+    },
+    /// Check a manifest's threshold, pass and eps against its own l and min_s, without the table
+    ValidateManifest {
         #[arg(long)] manifest: PathBuf,
-        #[arg(long, default_value_t = 0)] threads: usize,
+        /// Largest accepted |manifest eps - recomputed eps| (a different libm may differ in the last ULP)
+        #[arg(long, default_value_t = EPS_TOLERANCE, value_parser = parse_tolerance)] eps_tolerance: f64,
+        /// Require the manifest eps to equal the recomputed one bit for bit (no float slack)
+        #[arg(long, default_value_t = false, conflicts_with = "eps_tolerance")] exact_eps: bool,
     },
+    /// Verify every table_*.bin[.gz] in a directory against its cert_*.json and summarise
+    VerifyAll {
+        #[arg(long)] dir: PathBuf,
+        /// Only tables whose file name matches this pattern (`*` and `?` wildcards)
+        #[arg(long, value_name = "PATTERN")] filter: Option<String>,
+        /// Certificates verified at once; cores are split between them [default: min(certificates, cores)]
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))] jobs: Option<u64>,
+        /// Total worker threads; 0 = all cores [default: 0]
+        #[arg(long)] threads: Option<usize>,
+    },
+    /// Print the JSON Schema of the manifest format to stdout
+    ManifestSchema,
+    /// Print the table file format (header layout, body per version, trailer) to stdout
+    FormatSpec,
     /// Compute summary stats and histogram for a table file
     Stats {
-        /// Path to table file (v1 or v2)
+        /// Path to table file (v1 or v2); `-` reads it from stdin
         #[arg(long)] table: PathBuf,
         /// Number of bins in histogram
         #[arg(long, default_value_t = 50)] bins: usize,
         /// Output CSV for histogram (bin_lo,bin_hi,count)
         #[arg(long)] out_csv: Option<PathBuf>,
+        /// Histogram eps_i = s_i/L - log2(3) with this many bins instead of raw S
+        #[arg(long, value_name = "BINS")] eps_hist: Option<usize>,
+        /// Drift constant c for threshold floor(L*c)+1 and eps [default: log2(3)]
+        #[arg(long, value_parser = parse_log_constant, allow_hyphen_values = true)] log_constant: Option<f64>,
+        /// Only use an evenly strided sample of at most N entries (labelled as a sample)
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))] max_entries: Option<u64>,
+        /// Do not check the sha256 trailer (the file is still read up to the last entry)
+        #[arg(long, default_value_t = false)] no_hash: bool,
+        /// Count every distinct S value exactly (CSV s,count) instead of binning
+        #[arg(long, default_value_t = false, conflicts_with = "eps_hist")] exact_histogram: bool,
+        /// Largest max_S for a dense array in --exact-histogram; above it a sparse map is used
+        #[arg(long, value_name = "N", default_value_t = 1 << 20, requires = "exact_histogram")] max_value_cap: u32,
+        /// Write the summary figures as JSON (the --baseline input format)
+        #[arg(long, value_name = "PATH")] out_json: Option<PathBuf>,
+        /// Compare min/max/mean/stddev against an earlier --out-json file; fails if any moved
+        #[arg(long, value_name = "PREV_JSON")] baseline: Option<PathBuf>,
+        /// Largest allowed |delta| per figure for --baseline
+        #[arg(long, default_value_t = 1e-9, requires = "baseline")] tolerance: f64,
+        /// Report count, fraction and mean of the upper tail s_i >= C
+        #[arg(long, value_name = "C", visible_alias = "tail")] above: Option<u32>,
+        /// Draw the histogram as an ASCII bar chart on stderr (in addition to --out-csv)
+        #[arg(long, default_value_t = false, conflicts_with = "exact_histogram")] plot: bool,
+        /// Chart width in columns for --plot [default: $COLUMNS on a terminal, else 80]
+        #[arg(long, value_name = "COLS", requires = "plot", value_parser = clap::value_parser!(u16).range(20..))] width: Option<u16>,
+        /// Hint the OS to read the table ahead sequentially (posix_fadvise) where supported
+        #[arg(long, default_value_t = false)] prefetch: bool,
+        /// Approximate quantiles with a Greenwald-Khanna sketch in one streaming pass, without
+        /// holding the table in memory (no histogram or fixed-point notes)
+        #[arg(long, default_value_t = false, conflicts_with_all = ["out_csv", "eps_hist", "exact_histogram", "plot"])]
+        approx_quantiles: bool,
+        /// Rank error bound of --approx-quantiles, as a fraction of the entries
+        #[arg(long, value_name = "EPS", default_value_t = 0.001, value_parser = parse_quantile_error, requires = "approx_quantiles")]
+        quantile_error: f64,
+    },
+    /// Time the generation compute loop (no file IO): median over --iters after one warm-up
+    Bench {
+        /// [default: $COLLATZ_K, else 20]
+        #[arg(long)] k: Option<u32>,
+        /// [default: $COLLATZ_L, else 64]
+        #[arg(long)] l: Option<u32>,
+        #[arg(long, default_value_t = 5)] iters: usize,
+        /// Worker threads; 0 = all cores [default: 0]
+        #[arg(long)] threads: Option<usize>,
+        /// Begin each pass at this index and wrap around to cover all entries
+        #[arg(long, value_name = "N", default_value_t = 0)] start_index: u64,
+    },
+    /// Generate default-named certificates for every (K, L) pair into --out-dir
+    Sweep {
+        /// Comma-separated K values
+        #[arg(long, value_delimiter = ',', required = true)] k: Vec<u32>,
+        /// Comma-separated L values
+        #[arg(long, value_delimiter = ',', required = true)] l: Vec<u32>,
+        /// Worker threads; 0 = all cores [default: 0]
+        #[arg(long)] threads: Option<usize>,
+        /// Output directory [default: config output_dir, else .]
+        #[arg(long)] out_dir: Option<PathBuf>,
+        /// Skip pairs whose table and manifest already exist and verify cleanly
+        #[arg(long, default_value_t = false)] skip_existing: bool,
+    },
+    /// Print the strict threshold for L (and eps for a hypothetical min_S) without generating anything
+    Threshold {
+        /// [default: $COLLATZ_L, else 256]
+        #[arg(long)] l: Option<u32>,
+        /// Hypothetical min_S to evaluate eps and pass for
+        #[arg(long)] min_s: Option<u32>,
+        /// Drift constant c for threshold floor(L*c)+1 and eps [default: log2(3)]
+        #[arg(long, value_parser = parse_log_constant, allow_hyphen_values = true)] log_constant: Option<f64>,
+    },
+    /// Find the L values up to --l-max where pass flips for this K (min_S for every L in one pass)
+    FindBoundary {
+        /// [default: $COLLATZ_K, else 24]
+        #[arg(long)] k: Option<u32>,
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..=100_000))] l_max: u32,
+        #[arg(long, default_value_t = false)] include_even: bool,
+        /// Drift constant c for threshold floor(L*c)+1 [default: log2(3)]
+        #[arg(long, value_parser = parse_log_constant, allow_hyphen_values = true)] log_constant: Option<f64>,
+        /// Worker threads; 0 = all cores [default: 0]
+        #[arg(long)] threads: Option<usize>,
+        /// Also write every L as CSV (l,min_s,threshold,pass)
+        #[arg(long, value_name = "PATH")] out_csv: Option<PathBuf>,
+    },
+    /// Distribution of the per-step exponent e over every odd residue's first L steps, as CSV exponent,count
+    BitAnalysis {
+        /// [default: $COLLATZ_K, else 24]
+        #[arg(long)] k: Option<u32>,
+        /// [default: $COLLATZ_L, else 256]
+        #[arg(long)] l: Option<u32>,
+        /// Worker threads; 0 = all cores [default: 0]
+        #[arg(long)] threads: Option<usize>,
+        /// Write the CSV here instead of stdout
+        #[arg(long)] out: Option<PathBuf>,
+    },
+    /// Print the step-by-step trajectory of one table entry as CSV (step,m,e,s,next)
+    Trace {
+        /// Required unless $COLLATZ_K is set
+        #[arg(long)] k: Option<u32>,
+        /// Required unless $COLLATZ_L is set
+        #[arg(long)] l: Option<u32>,
+        /// Table index (the residue is (index<<1)|1, or index itself with --include-even)
+        #[arg(long)] index: u64,
+        #[arg(long, default_value_t = false)] include_even: bool,
+        /// Write the CSV here instead of stdout
+        #[arg(long, conflicts_with = "summary")] out: Option<PathBuf>,
+        /// Only print total S, the count of steps with e >= 2, the largest e and the final residue
+        #[arg(long, default_value_t = false)] summary: bool,
+    },
+    /// Print log2(3), the drift constant behind every threshold, to --digits decimal places (truncated)
+    Constant {
+        #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u64).range(1..=5000))] digits: u64,
+    },
+    /// Rewrite a ver=1/2 table as a run-length encoded ver=4 table (and its manifest to match)
+    Compact {
+        #[arg(long)] table: PathBuf,
+        #[arg(long)] out: PathBuf,
+        /// Manifest of --table, rewritten for the compacted table into --out-manifest
+        #[arg(long, requires = "out_manifest")] manifest: Option<PathBuf>,
+        #[arg(long, requires = "manifest")] out_manifest: Option<PathBuf>,
+    },
+    /// Are two tables identical? Compares K/L/count, then the (validated) sha256 trailers
+    Equal {
+        /// First table (plain or gzip)
+        #[arg(long)] a: PathBuf,
+        /// Second table (plain or gzip)
+        #[arg(long)] b: PathBuf,
+    },
+    /// Stream a table's values in index order (index,s CSV or raw u32s), in constant memory
+    Export {
+        /// Table file; `-` reads it from stdin
+        #[arg(long)] table: PathBuf,
+        /// Output file; stdout when omitted or `-`
+        #[arg(long)] out: Option<PathBuf>,
+        /// csv: index,s rows; raw-le: bare little-endian u32 S values, no header or trailer
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)] format: ExportFormat,
+        /// Omit the index,s header row (csv)
+        #[arg(long, default_value_t = false)] no_header: bool,
+    },
+    /// Build a ver=2 table from index,s CSV rows (the export format, any row order)
+    Import {
+        /// CSV file; `-` reads it from stdin
+        #[arg(long)] csv: PathBuf,
+        #[arg(long)] k: u32,
+        #[arg(long)] l: u32,
+        #[arg(long, default_value_t = false)] include_even: bool,
+        /// Table file to write
+        #[arg(long, required_unless_present = "validate_only")] out: Option<PathBuf>,
+        /// Only check that every index in [0,count) appears once with a u32 value; write nothing
+        #[arg(long, default_value_t = false, conflicts_with = "out")] validate_only: bool,
     },
     /// Pack table+manifest into tar.gz and emit sha256; optionally write CHECKSUMS.sha256
     Pack {
-        #[arg(long)] table: PathBuf,
+        /// Table to bundle (with --manifest-only, only checked against the manifest)
+        #[arg(long, required_unless_present = "manifest_only")] table: Option<PathBuf>,
         #[arg(long)] manifest: PathBuf,
         #[arg(long)] out: Option<PathBuf>,
         /// Also write CHECKSUMS.sha256 next to archive
         #[arg(long, default_value_t = false)] checksums: bool,
+        /// Store the files under this relative directory inside the archive instead of at its root
+        #[arg(long, value_name = "DIR")] tar_root: Option<PathBuf>,
+        /// Check the inputs and print the planned archive path and entries without writing anything
+        #[arg(long, default_value_t = false)] dry_run: bool,
+        /// With --dry-run, only read the table header instead of checking the sha256 trailer
+        #[arg(long, default_value_t = false, requires = "dry_run")] no_hash: bool,
+        /// Archive only the manifest (consumers regenerate the table); --table, if given, is checked
+        #[arg(long, default_value_t = false)] manifest_only: bool,
+    },
+    /// Store many tables (with the manifests found next to them) in one bundle file for random access
+    Bundle {
+        /// Table files; each one's cert_k*_l*_v2.json in the same directory is stored with it
+        #[arg(required = true)] inputs: Vec<PathBuf>,
+        #[arg(long)] out: PathBuf,
+    },
+    /// Extract tables and manifests from a bundle (all entries, or one with --k/--l)
+    Unbundle {
+        #[arg(long)] bundle: PathBuf,
+        #[arg(long, requires = "l")] k: Option<u32>,
+        #[arg(long, requires = "k")] l: Option<u32>,
+        /// Directory for the extracted files, named as gen names them
+        #[arg(long, default_value = ".")] out_dir: PathBuf,
+    },
+    /// Check a bundle's directory and entries: sha256s, table headers and trailers, manifests
+    VerifyBundle {
+        #[arg(long)] bundle: PathBuf,
+        /// Only check this entry (the directory is always checked)
+        #[arg(long, requires = "l")] k: Option<u32>,
+        #[arg(long, requires = "k")] l: Option<u32>,
     },
 }
 
-#[repr(C)]
-#[derive(Clone, Copy)]
-struct Header {
-    magic: [u8; 4],
-    ver: u32,
-    k: u32,
-    l: u32,
-    count: u64,
-    _reserved: [u8; 8],
-}
-
+/// Progress sidecar for `gen --checkpoint`: entries `[0, next_index)` are durably written.
 #[derive(Serialize, Deserialize)]
-struct Manifest {
+struct Checkpoint {
     k: u32,
     l: u32,
-    count: u64,
+    file_ver: u32,
+    table: String,
+    next_index: u64,
     min_s: u32,
-    eps: f64,
-    threshold: u32,
-    pass: bool,
-    sha256_table_hex: String,
-    sha256_exec_hex: String,
-    generator_cmdline: String,
-    pkg_version: String,
-    build_git_rev: String,
-    build_rustc: String,
-    os_arch: String,
-    gen_ts: String,
+    /// Lowest index attaining min_s so far
     #[serde(default)]
-    file_ver: u32,
+    min_s_index: Option<u64>,
+    #[serde(default)]
+    max_s: Option<u32>,
+    /// Exact sum of S over `[0, next_index)`
+    #[serde(default)]
+    sum_s: Option<u64>,
+    #[serde(default)]
+    neg_drift_count: Option<u64>,
+    /// Drift constant the negative-drift count so far was taken against
+    #[serde(default)]
+    log_constant: Option<f64>,
+    #[serde(default)]
+    include_even: Option<bool>,
+}
+
+/// Defaults loaded from `--config`; every field is optional and command-line flags win.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    threads: Option<usize>,
+    output_dir: Option<PathBuf>,
+    hash: Option<String>,
+    threshold_mode: Option<String>,
+}
+
+impl Config {
+    /// Parse the flat `key = value` subset of TOML (integers, booleans, quoted strings,
+    /// `#` comments); values are then typed through serde like any other config source.
+    fn load(path: &Path) -> anyhow::Result<Config> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("config {}: {e}", path.display()))?;
+        let mut map = serde_json::Map::new();
+        for (n, raw) in text.lines().enumerate() {
+            let line = match raw.find('#') {
+                Some(i) if raw[..i].matches('"').count() % 2 == 0 => &raw[..i],
+                _ => raw,
+            }.trim();
+            if line.is_empty() { continue; }
+            let (key, val) = line.split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("config {}:{}: expected key = value", path.display(), n + 1))?;
+            let val = val.trim();
+            let v = if let Some(q) = val.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                serde_json::Value::String(q.to_string())
+            } else if let Ok(i) = val.parse::<u64>() {
+                serde_json::Value::from(i)
+            } else if let Ok(b) = val.parse::<bool>() {
+                serde_json::Value::Bool(b)
+            } else {
+                anyhow::bail!("config {}:{}: unsupported value `{val}`", path.display(), n + 1);
+            };
+            map.insert(key.trim().to_string(), v);
+        }
+        let cfg: Config = serde_json::from_value(serde_json::Value::Object(map))
+            .map_err(|e| anyhow::anyhow!("config {}: {e}", path.display()))?;
+        if let Some(h) = &cfg.hash {
+            anyhow::ensure!(h == "sha256", "config: unsupported hash `{h}` (only sha256)");
+        }
+        if let Some(t) = &cfg.threshold_mode {
+            anyhow::ensure!(t == "strict", "config: unsupported threshold_mode `{t}` (only strict)");
+        }
+        Ok(cfg)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, clap::ValueEnum)]
+enum ErrorFormat {
+    Text,
+    Json,
 }
 
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
+    let error_format = args.error_format;
+    let checking = matches!(args.cmd, Cmd::Verify { .. } | Cmd::VerifyAll { .. } | Cmd::ValidateManifest { .. } | Cmd::Equal { .. } | Cmd::VerifyBundle { .. });
+    match run(args) {
+        Err(e) if error_format == ErrorFormat::Json => {
+            let kind = error_kind(&e, checking);
+            println!("{}", serde_json::json!({ "error": format!("{e:#}"), "kind": kind }));
+            std::process::exit(if kind == "interrupted" { 130 } else { 1 });
+        }
+        Err(e) if e.is::<Interrupted>() => {
+            eprintln!("{e}");
+            std::process::exit(130);
+        }
+        r => r,
+    }
+}
+
+/// `--error-format json` kind: what the error chain says it is, else "verification" for the
+/// checking commands (a failed check is their error) and "error" for the rest.
+fn error_kind(e: &anyhow::Error, checking: bool) -> &'static str {
+    for cause in e.chain() {
+        if cause.is::<Interrupted>() {
+            return "interrupted";
+        }
+        if cause.is::<serde_json::Error>() {
+            return "parse";
+        }
+        if cause.is::<std::io::Error>() {
+            return "io";
+        }
+    }
+    if checking { "verification" } else { "error" }
+}
+
+fn run(args: Args) -> anyhow::Result<()> {
+    if args.no_color {
+        anstream::ColorChoice::Never.write_global();
+    }
+    let cfg = match &args.config {
+        Some(p) => Config::load(p)?,
+        None => Config::default(),
+    };
     match args.cmd {
-        Cmd::Gen { k, l, threads, out_table, out_manifest } =>
-            gen(k, l, threads, out_table, out_manifest),
-        Cmd::Verify { k, l, table, manifest, threads } =>
-            verify(k, l, table, manifest, threads),
-        Cmd::Stats { table, bins, out_csv } => stats(table, bins, out_csv),
-        Cmd::Pack { table, manifest, out, checksums } => pack(table, manifest, out, checksums),
+        Cmd::Gen { k, l, threads, out_table, out_manifest, checkpoint, include_even, metrics, manifest_stdout, table_stdout, log_constant, note, verify_after, require_pass, throttle_eps, profile, profile_json, with_state, extend_from, dump_worst, dump_failing, max_failing, events_json, pipeline, summary_line, max_index } => {
+            let (k, l) = (k_or_env(k, Some(24))?, l_or_env(l, Some(256))?);
+            let out_table = out_table.or_else(|| cfg.output_dir.as_ref().map(|d| d.join(default_table_name(k, l))));
+            let out_manifest = match (out_manifest, manifest_stdout) {
+                (Some(p), _) => Some(p),
+                (None, true) => None,
+                (None, false) => cfg.output_dir.as_ref().map(|d| d.join(default_manifest_name(k, l))),
+            };
+            let residues = if include_even { Residues::All } else { Residues::Odd };
+            let events = Events::open(events_json.as_deref())?;
+            let r = gen(k, l, threads.or(cfg.threads).unwrap_or(0),
+                GenOpts {
+                    out_table, out_manifest, checkpoint, residues, metrics, manifest_stdout, table_stdout,
+                    log_constant: log_constant.unwrap_or_else(log2_3),
+                    note,
+                    verify_after,
+                    require_pass,
+                    throttle_eps,
+                    profile: profile || profile_json.is_some(),
+                    profile_json,
+                    with_state: with_state || extend_from.is_some(),
+                    extend_from,
+                    dump_worst,
+                    dump_failing: dump_failing.map(|p| (p, max_failing)),
+                    events: events.clone(),
+                    pipeline,
+                    summary_line,
+                    max_index,
+                });
+            events.fail_on(r)
+        }
+        Cmd::Verify { no_table: true, manifest: Some(manifest), threads, recompute_threads, eps_tolerance, exact_eps, min_margin, .. } =>
+            verify_no_table(&manifest, recompute_threads.or(threads).or(cfg.threads).unwrap_or(0), eps_tolerance_of(eps_tolerance, exact_eps), min_margin),
+        Cmd::Verify { table: None, .. } => unreachable!("clap requires --table or --no-table with --manifest"),
+        Cmd::Verify { k, l, table: Some(table), structural_only: true, .. } => verify_structural(k, l, &table),
+        Cmd::Verify { k, l, table: Some(table), manifest, threads, index_range, sample, seed, diagnose, metrics, first_fail, compare_manifest, events_json, direct, verify_build, explain, eps_tolerance, exact_eps, repeat, summary_line, expect_min_s, io_threads, recompute_threads, prefetch, min_margin, rewrite_manifest, .. } => {
+            let events = Events::open(events_json.as_deref())?;
+            let threads = threads.or(cfg.threads).unwrap_or(0);
+            if let Some(n) = io_threads.filter(|&n| n == 0 || n > IO_STAGE_TASKS) {
+                eprintln!("io-threads: {n} requested; the read stage is {IO_STAGE_TASKS} tasks (sha256, decode), using at most {IO_STAGE_TASKS}");
+            }
+            let r = verify(k, l, table, manifest.clone(), recompute_threads.unwrap_or(threads),
+                   VerifyOpts {
+                       index_range, sample, seed, diagnose, metrics, first_fail, events: events.clone(), direct, explain,
+                       eps_tolerance: eps_tolerance_of(eps_tolerance, exact_eps), repeat: repeat as usize, summary_line,
+                       expect_min_s, io_threads: Some(io_threads.unwrap_or(threads)), prefetch,
+                       min_margin, rewrite_manifest,
+                   });
+            // the verdict is on stderr; pass=false is a valid certificate of a failing K/L
+            events.fail_on(r.map(|_| ()))?;
+            // both flags require --manifest
+            if let (true, Some(m)) = (verify_build, &manifest) {
+                check_build(m)?;
+            }
+            match (compare_manifest, &manifest) {
+                (Some(other), Some(m)) => compare_manifests(m, &other),
+                _ => Ok(()),
+            }
+        }
+        Cmd::ValidateManifest { manifest, eps_tolerance, exact_eps } =>
+            validate_manifest(&manifest, eps_tolerance_of(eps_tolerance, exact_eps)),
+        Cmd::VerifyAll { dir, filter, jobs, threads } =>
+            verify_all(&dir, filter.as_deref(), jobs.map(|j| j as usize), threads.or(cfg.threads).unwrap_or(0)),
+        Cmd::ManifestSchema => {
+            println!("{}", serde_json::to_string_pretty(&manifest_schema())?);
+            Ok(())
+        }
+        Cmd::FormatSpec => {
+            print!("{}", collatz_cert::format_spec());
+            Ok(())
+        }
+        Cmd::Stats { table, bins, out_csv, eps_hist, log_constant, max_entries, no_hash, exact_histogram, max_value_cap, out_json, baseline, tolerance, above, plot, width, prefetch, approx_quantiles, quantile_error } =>
+            stats(table, bins, out_csv, eps_hist, log_constant.unwrap_or_else(log2_3),
+                  StatsOpts {
+                      max_entries, no_hash, exact_histogram, max_value_cap, out_json, baseline, tolerance, above,
+                      plot_width: plot.then(|| plot_width(width)), prefetch,
+                      approx_quantiles: approx_quantiles.then_some(quantile_error),
+                  }),
+        Cmd::Bench { k, l, iters, threads, start_index } =>
+            bench(k_or_env(k, Some(20))?, l_or_env(l, Some(64))?, iters, threads.or(cfg.threads).unwrap_or(0), start_index),
+        Cmd::Sweep { k, l, threads, out_dir, skip_existing } => {
+            let out_dir = out_dir.or(cfg.output_dir).unwrap_or_else(|| PathBuf::from("."));
+            sweep(&k, &l, threads.or(cfg.threads).unwrap_or(0), &out_dir, skip_existing)
+        }
+        Cmd::Threshold { l, min_s, log_constant } => threshold(l_or_env(l, Some(256))?, min_s, log_constant.unwrap_or_else(log2_3)),
+        Cmd::FindBoundary { k, l_max, include_even, log_constant, threads, out_csv } =>
+            find_boundary(k_or_env(k, Some(24))?, l_max, if include_even { Residues::All } else { Residues::Odd },
+                          log_constant.unwrap_or_else(log2_3), threads.or(cfg.threads).unwrap_or(0), out_csv.as_deref()),
+        Cmd::BitAnalysis { k, l, threads, out } =>
+            bit_analysis(k_or_env(k, Some(24))?, l_or_env(l, Some(256))?, threads.or(cfg.threads).unwrap_or(0), out.as_deref()),
+        Cmd::Trace { k, l, index, include_even, out, summary } =>
+            trace_cmd(k_or_env(k, None)?, l_or_env(l, None)?, if include_even { Residues::All } else { Residues::Odd }, index, out.as_deref(), summary),
+        Cmd::Constant { digits } => {
+            println!("{}", log2_3_digits(digits as usize));
+            Ok(())
+        }
+        Cmd::Compact { table, out, manifest, out_manifest } =>
+            compact(&table, &out, manifest.as_deref().zip(out_manifest.as_deref())),
+        Cmd::Equal { a, b } => equal(&a, &b),
+        Cmd::Export { table, out, format, no_header } =>
+            export(&table, out.as_deref().filter(|p| !is_stdin(p)), format, no_header),
+        Cmd::Import { csv, k, l, include_even, out, validate_only } =>
+            import(&csv, k, l, if include_even { Residues::All } else { Residues::Odd }, out.as_deref().filter(|_| !validate_only)),
+        Cmd::Pack { table, manifest, out, checksums, tar_root, dry_run, no_hash, manifest_only } =>
+            pack(table, manifest, out, PackOpts { checksums, tar_root, dry_run, no_hash, manifest_only }),
+        Cmd::Bundle { inputs, out } => bundle(&inputs, &out),
+        Cmd::Unbundle { bundle, k, l, out_dir } => unbundle(&bundle, k.zip(l), &out_dir),
+        Cmd::VerifyBundle { bundle, k, l } => verify_bundle(&bundle, k.zip(l)),
+    }
+}
+
+/// `--k` for gen, bench and trace: the flag, else `$COLLATZ_K` (logged), else `default`.
+fn k_or_env(flag: Option<u32>, default: Option<u32>) -> anyhow::Result<u32> {
+    param_or_env("k", flag, "COLLATZ_K", default)
+}
+
+/// `--l` for gen, bench, trace and threshold: the flag, else `$COLLATZ_L` (logged), else `default`.
+fn l_or_env(flag: Option<u32>, default: Option<u32>) -> anyhow::Result<u32> {
+    param_or_env("l", flag, "COLLATZ_L", default)
+}
+
+/// A flag takes precedence over the environment, which takes precedence over the built-in
+/// default; an empty variable counts as unset. Values taken from the environment are logged
+/// so a shell session's defaults are never silent.
+fn param_or_env(name: &str, flag: Option<u32>, var: &str, default: Option<u32>) -> anyhow::Result<u32> {
+    if let Some(v) = flag {
+        return Ok(v);
+    }
+    match std::env::var(var) {
+        Ok(raw) if !raw.trim().is_empty() => {
+            let v = raw.trim().parse().map_err(|e| anyhow::anyhow!("{var}={raw:?}: {e}"))?;
+            eprintln!("note: {name}={v} from {var} (pass --{name} to override)");
+            Ok(v)
+        }
+        Err(std::env::VarError::NotUnicode(_)) => anyhow::bail!("{var} is not valid unicode"),
+        _ => default.ok_or_else(|| anyhow::anyhow!("--{name} is required (or set {var})")),
     }
 }
 
-fn gen(k: u32, l: u32, threads: usize, out_table: Option<PathBuf>, out_manifest: Option<PathBuf>) -> anyhow::Result<()> {
+/// Residues per chunk in streaming gen: bounds peak memory to one chunk of u32s.
+const GEN_CHUNK: usize = 1 << 20;
+
+/// Optional gen inputs; `None` paths fall back to the default file names.
+struct GenOpts {
+    out_table: Option<PathBuf>,
+    out_manifest: Option<PathBuf>,
+    checkpoint: Option<PathBuf>,
+    residues: Residues,
+    metrics: Option<PathBuf>,
+    /// Print the manifest to stdout; the file is then only written for an explicit path
+    manifest_stdout: bool,
+    /// Stream the table to stdout instead of a file
+    table_stdout: bool,
+    /// Drift constant c (log2(3) for 3x+1)
+    log_constant: f64,
+    note: Option<String>,
+    /// Re-read and fully verify the written files
+    verify_after: bool,
+    /// Fail once everything is written if pass=false
+    require_pass: bool,
+    /// Target entries per second; None runs flat out
+    throttle_eps: Option<u64>,
+    /// Time the phases of the chunk loop
+    profile: bool,
+    profile_json: Option<PathBuf>,
+    /// Write ver=3 (s, m) entries
+    with_state: bool,
+    /// ver=3 table with a smaller l to continue from
+    extend_from: Option<PathBuf>,
+    /// CSV path for the trajectory of the min_S residue
+    dump_worst: Option<PathBuf>,
+    /// Sidecar for the indices with S below threshold, and how many to keep
+    dump_failing: Option<(PathBuf, usize)>,
+    events: Events,
+    /// Overlap hashing/writing of chunk N with computing chunk N+1
+    pipeline: bool,
+    summary_line: bool,
+    /// Stop after this many residues and flag the table partial
+    max_index: Option<u64>,
+}
+
+fn gen(k: u32, l: u32, threads: usize, opts: GenOpts) -> anyhow::Result<()> {
+    let GenOpts { out_table, out_manifest, checkpoint, residues, metrics, manifest_stdout, table_stdout, log_constant, note, verify_after, require_pass, throttle_eps, profile, profile_json, with_state, extend_from, dump_worst, dump_failing, events, pipeline, summary_line, max_index } = opts;
+    let started = std::time::Instant::now();
     anyhow::ensure!((2..=28).contains(&k), "k in [2,28]");
-    anyhow::ensure!(l >= 1, "l >= 1");
+    // before any file is created: a threshold out of range is refused, never wrapped
+    let thr = threshold_strict(l, log_constant)?;
+    anyhow::ensure!(
+        !(verify_after && manifest_stdout && out_manifest.is_none()),
+        "--verify-after needs a manifest file: add --out-manifest"
+    );
 
     let nthreads = if threads == 0 {
         std::thread::available_parallelism()?.get()
     } else { threads };
     eprintln!("threads={}", nthreads);
 
-    let count: u64 = 1u64 << (k - 1);
+    let full = residues.count(k);
+    let count: u64 = max_index.map_or(full, |n| min(n, full));
+    let partial = count < full;
+    if partial {
+        eprintln!("partial: only residues [0, {count}) of {full}; the table is flagged partial and certifies nothing");
+    }
     let mask: u64 = (1u64 << k) - 1;
 
-    let min_s_atomic = std::sync::atomic::AtomicU32::new(u32::MAX);
-    let mut table: Vec<u32> = vec![0; count as usize];
-
-    let pool = rayon::ThreadPoolBuilder::new().num_threads(nthreads).build()?;
-    pool.install(|| {
-        table.par_iter_mut().enumerate().for_each(|(idx, slot)| {
-            let mut m = ((idx as u64) << 1) | 1;
-            let mut s: u64 = 0;
-            for _ in 0..l {
-                let t = 3u64.wrapping_mul(m & mask).wrapping_add(1);
-                let e = t.trailing_zeros() as u64;
-                s += e;
-                m = (t >> e) & mask;
-            }
-            let s32 = s.min(u32::MAX as u64) as u32;
-            *slot = s32;
-            loop {
-                let cur = min_s_atomic.load(std::sync::atomic::Ordering::Relaxed);
-                if s32 < cur {
-                    if min_s_atomic.compare_exchange(
-                        cur, s32,
-                        std::sync::atomic::Ordering::Relaxed,
-                        std::sync::atomic::Ordering::Relaxed
-                    ).is_ok() { break; }
-                } else { break; }
-            }
-        });
-    });
-
-    // v2 format uses u32 entries; no overflow clipping required
-
-    // header (v2 format: u32 entries)
-    let file_ver: u32 = 2;
+    // header (v2 format: u32 entries; v3 with --with-state: u32 s + u32 end residue)
+    let file_ver: u32 = if with_state { VER_STATE } else { 2 };
     let header = Header {
         magic: *b"CALT",
         ver: file_ver,
         k,
         l,
         count,
-        _reserved: [0u8; 8],
+        flags: residues.flag() | if partial { FLAG_PARTIAL } else { 0 },
+        algo_ver: ALGO_VER,
+        _reserved: [0u8; 2],
     };
 
-    // stream write with hashing to reduce peak memory
-    let out_table = out_table.unwrap_or_else(|| PathBuf::from(format!("table_k{}_l{}_v2.bin", k, l)));
-    let mut f = std::io::BufWriter::new(File::create(&out_table)?);
-    f.write_all(&header.magic)?;
-    f.write_all(&header.ver.to_le_bytes())?;
-    f.write_all(&header.k.to_le_bytes())?;
-    f.write_all(&header.l.to_le_bytes())?;
-    f.write_all(&header.count.to_le_bytes())?;
-    f.write_all(&header._reserved)?;
+    // stream write with hashing: residues are computed in index-ordered chunks,
+    // each chunk is hashed and written before the next one is computed
+    let out_table = (!table_stdout)
+        .then(|| out_table.unwrap_or_else(|| PathBuf::from(default_table_name(k, l))));
+    let resumed = match (&checkpoint, &out_table) {
+        (Some(cp), Some(t)) => resume_checkpoint(cp, &header, t, log_constant)?,
+        _ => None,
+    };
+    /// The table sink; `sync` is the table file handle used to make checkpoints durable
+    /// (None for stdout).
+    struct Writer {
+        out: TableSink,
+        sync: Option<File>,
+        hasher: Sha256,
+        progress: Progress,
+    }
+    let Writer { out: f, sync, hasher, progress } = match (resumed, &out_table) {
+        (Some(GenState { file, hasher, progress }), _) =>
+            Writer { out: Box::new(std::io::BufWriter::new(file.try_clone()?)), sync: Some(file), hasher, progress },
+        (None, Some(t)) => {
+            let file = File::create(t)?;
+            let mut f = std::io::BufWriter::new(file.try_clone()?);
+            write_header(&mut f, &header)?;
+            Writer { out: Box::new(f), sync: Some(file), hasher: Sha256::new(), progress: Progress::FRESH }
+        }
+        (None, None) => {
+            let mut f = std::io::BufWriter::new(std::io::stdout());
+            write_header(&mut f, &header)?;
+            Writer { out: Box::new(f), sync: None, hasher: Sha256::new(), progress: Progress::FRESH }
+        }
+    };
+    let Progress { next: mut start, mut min_s, mut argmin, mut max_s, mut sum_s, mut neg_drift } = progress;
 
-    let mut hasher = Sha256::new();
-    for &v in &table {
-        let bytes = (v as u32).to_le_bytes();
-        hasher.update(&bytes);
-        f.write_all(&bytes)?;
+    // --extend-from: entries are read in step with the chunks being generated
+    let mut base = match &extend_from {
+        Some(p) => {
+            let mut r = TableReader::open(p)?;
+            let h = *r.header();
+            anyhow::ensure!(h.ver == VER_STATE, "--extend-from {}: needs a ver=3 table (gen --with-state), got ver={}", p.display(), h.ver);
+            anyhow::ensure!(
+                h.k == k && h.residues() == residues,
+                "--extend-from {}: table has k={} ({:?} residues), expected k={k} ({residues:?})", p.display(), h.k, h.residues()
+            );
+            anyhow::ensure!(h.l < l, "--extend-from {}: table l={} must be below --l {l}", p.display(), h.l);
+            anyhow::ensure!(
+                h.algo_compatible(),
+                "--extend-from {}: table algo_ver={} is incompatible with this build (algo_ver={ALGO_VER})", p.display(), h.algo_ver
+            );
+            for _ in 0..start {
+                read_base_entry(&mut r)?;
+            }
+            eprintln!("extend: continuing l={} -> l={l} from {}", h.l, p.display());
+            Some((h.l, r))
+        }
+        None => None,
+    };
+
+    events.emit("start", serde_json::json!({
+        "cmd": "gen", "k": k, "l": l, "count": count, "threads": nthreads, "file_ver": file_ver, "resume_index": start,
+    }))?;
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(nthreads).build()?;
+    let walk = Walk { mask, l, residues, neg_bound: neg_drift_bound(l, log_constant) };
+    // --dump-failing: lowest `cap` indices below threshold, plus how many there are in all
+    let (mut failing, mut failing_total) = (Vec::<u64>::new(), 0u64);
+    let mut chunk: Vec<u32> = Vec::with_capacity(min(GEN_CHUNK as u64, count) as usize);
+    let mut ends: Vec<u32> = Vec::new();
+    let mut base_chunk: Vec<(u32, u32)> = Vec::new();
+    let mut bytes: Vec<u8> = Vec::with_capacity(chunk.capacity() * 4);
+    // --throttle-eps: chunks of at most one second's worth, then sleep until back on schedule
+    let chunk_cap = throttle_eps.map_or(GEN_CHUNK as u64, |n| min(n, GEN_CHUNK as u64));
+    if let Some(n) = throttle_eps {
+        eprintln!("throttle: target {n} entries/s");
+    }
+    let (run_start, run_from) = (std::time::Instant::now(), start);
+    let mut prof = Profile::new(profile);
+    let mut w = ChunkWriter::new(f, hasher, pipeline);
+    interrupt::install();
+    // one more pass after the last chunk when a signal arrived during it, so it is not lost
+    while start < count || interrupt::requested() {
+        if interrupt::requested() {
+            // stop at a chunk boundary; never leave a table that looks complete
+            drop(w.finish());
+            match (&out_table, &checkpoint) {
+                (Some(t), Some(cp)) => eprintln!("gen: kept {} and {} for resuming", t.display(), cp.display()),
+                (Some(t), None) => {
+                    std::fs::remove_file(t)?;
+                    eprintln!("gen: removed partial {}", t.display());
+                }
+                (None, _) => {}
+            }
+            return Err(Interrupted.into());
+        }
+        prof.lap(None);
+        let len = min(chunk_cap, count - start) as usize;
+        chunk.clear();
+        chunk.resize(len, 0);
+        let summary = if with_state {
+            ends.clear();
+            ends.resize(len, 0);
+            let from = match &mut base {
+                Some((l0, r)) => {
+                    base_chunk.clear();
+                    for _ in 0..len {
+                        base_chunk.push(read_base_entry(r)?);
+                    }
+                    Some((*l0, &base_chunk[..]))
+                }
+                None => None,
+            };
+            prof.lap(Some(Phase::Io));
+            pool.install(|| compute_chunk_state(&mut chunk, &mut ends, start, walk, from))
+        } else {
+            pool.install(|| compute_chunk(&mut chunk, start, walk))
+        };
+        if summary.min < min_s {
+            min_s = summary.min;
+            argmin = Some(summary.argmin);
+        }
+        max_s = max_s.map(|m| max(m, summary.max));
+        sum_s = sum_s.map(|t| t + summary.sum);
+        neg_drift = neg_drift.map(|n| n + summary.neg);
+        if let Some((_, cap)) = &dump_failing {
+            if summary.min < thr {
+                let below: Vec<u64> = pool.install(|| {
+                    chunk.par_iter().enumerate().filter(|&(_, &s)| s < thr).map(|(i, _)| start + i as u64).collect()
+                });
+                failing_total += below.len() as u64;
+                let room = cap.saturating_sub(failing.len());
+                failing.extend_from_slice(&below[..min(room, below.len())]);
+            }
+        }
+        prof.lap(Some(Phase::Compute));
+
+        bytes.clear();
+        for (i, &v) in chunk.iter().enumerate() {
+            bytes.extend_from_slice(&v.to_le_bytes());
+            if with_state {
+                bytes.extend_from_slice(&ends[i].to_le_bytes());
+            }
+        }
+        prof.lap(Some(Phase::Io));
+        w.put(&mut bytes, &mut prof)?;
+        start += len as u64;
+        events.emit("progress", serde_json::json!({
+            "done": start, "count": count, "fraction": start as f64 / count as f64, "min_s": min_s,
+        }))?;
+        if let Some(n) = throttle_eps {
+            let due = std::time::Duration::from_secs_f64((start - run_from) as f64 / n as f64);
+            if let Some(ahead) = due.checked_sub(run_start.elapsed()) {
+                std::thread::sleep(ahead);
+            }
+        }
+        prof.lap(None);
+
+        if let (Some(cp), Some(t), Some(sync)) = (&checkpoint, &out_table, &sync) {
+            if start < count {
+                w.flush()?;
+                sync.sync_data()?;
+                write_checkpoint(cp, &Checkpoint {
+                    k,
+                    l,
+                    file_ver,
+                    table: t.display().to_string(),
+                    next_index: start,
+                    min_s,
+                    min_s_index: argmin,
+                    max_s,
+                    sum_s,
+                    neg_drift_count: neg_drift,
+                    log_constant: Some(log_constant),
+                    include_even: Some(residues == Residues::All),
+                })?;
+            }
+        }
+        prof.lap(Some(Phase::Io));
+    }
+    interrupt::restore();
+    if let Some((_, r)) = &mut base {
+        // past the last entry: checks the source trailer
+        if let Some(e) = r.next_entry() {
+            e?;
+        }
     }
+    prof.lap(Some(Phase::Io));
+    let (mut f, hasher) = w.finish()?;
     let digest = hasher.finalize();
+    prof.lap(Some(Phase::Hash));
     f.write_all(&digest)?;
     f.flush()?;
+    prof.lap(Some(Phase::Io));
 
-    let min_s = min_s_atomic.load(std::sync::atomic::Ordering::Relaxed);
-    let thr = threshold_strict(l);
-    let pass = (min_s as u32) >= thr;
-    let eps = (min_s as f64) / (l as f64) - log2_3();
+    // a partial table certifies nothing, whatever its prefix holds
+    let pass = !partial && min_s >= thr;
+    let eps = drift_eps(min_s, l, log_constant);
 
     let exe = std::env::current_exe()?;
     let sha_exec = sha256_file(&exe).unwrap_or_else(|_| "unknown".into());
     let ts = chrono::Utc::now().to_rfc3339();
+    // the sum is exact in u64 (at most 2^28 entries of u32), so the mean is reproducible
+    let mean_s = sum_s.map(|t| t as f64 / count as f64);
 
-    let out_manifest = out_manifest.unwrap_or_else(|| PathBuf::from(format!("cert_k{}_l{}_v2.json", k, l)));
-    let manifest = Manifest {
+    let out_manifest = match out_manifest {
+        Some(p) => Some(p),
+        None if manifest_stdout => None,
+        None => Some(PathBuf::from(default_manifest_name(k, l))),
+    };
+    let mut manifest = Manifest {
         k,
         l,
         count,
         min_s,
+        max_s,
+        mean_s,
+        neg_drift_count: neg_drift,
         eps,
         threshold: thr,
         pass,
         sha256_table_hex: hex(&digest),
         sha256_exec_hex: sha_exec,
         generator_cmdline: std::env::args().collect::<Vec<_>>().join(" "),
+        reproduce_cmd: reproduce_cmd(k, l, residues, with_state, partial.then_some(count)),
         pkg_version: env!("CARGO_PKG_VERSION").to_string(),
-        build_git_rev: option_env!("BUILD_GIT_REV").unwrap_or("unknown").to_string(),
-        build_rustc: option_env!("BUILD_RUSTC").unwrap_or("unknown").to_string(),
+        build_git_rev: BUILD_GIT_REV.to_string(),
+        build_rustc: BUILD_RUSTC.to_string(),
         os_arch: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
         gen_ts: ts,
-        file_ver: file_ver,
+        file_ver,
+        include_even: residues == Residues::All,
+        modulus_bits: Some(k),
+        residue_form: Some(residues.form().to_string()),
+        log_constant,
+        peak_rss_bytes: peak_rss_bytes(),
+        notes: note,
+        manifest_sha256: None,
     };
-    let mut mf = File::create(&out_manifest)?;
-    serde_json::to_writer_pretty(&mut mf, &manifest)?;
-    mf.flush()?;
+    manifest.manifest_sha256 = Some(manifest_sha256(&manifest));
+    if let Some(out_manifest) = &out_manifest {
+        let mut mf = File::create(out_manifest)?;
+        serde_json::to_writer_pretty(&mut mf, &manifest)?;
+        mf.flush()?;
+    }
+    if manifest_stdout {
+        let mut out = std::io::stdout().lock();
+        serde_json::to_writer_pretty(&mut out, &manifest)?;
+        writeln!(out)?;
+    }
 
-    eprintln!("OK gen: min_S={min_s} thr={thr} pass={pass} eps={:.6}", eps);
+    if let Some(cp) = &checkpoint {
+        if cp.exists() { std::fs::remove_file(cp)?; }
+    }
+
+    if let Some(p) = &metrics {
+        write_metrics(p, k, l, log_constant, min_s, pass)?;
+    }
+    if let Some((p, cap)) = &dump_failing {
+        let mut w = std::io::BufWriter::new(File::create(p).map_err(path_io_error(p))?);
+        for idx in &failing {
+            writeln!(w, "{idx}")?;
+        }
+        w.flush()?;
+        if failing_total == 0 {
+            eprintln!("dump-failing: no residues below thr={thr} (pass=true); wrote empty {}", p.display());
+        } else if failing_total > failing.len() as u64 {
+            eprintln!("dump-failing: wrote the lowest {} of {failing_total} failing indices (--max-failing {cap})", failing.len());
+        } else {
+            eprintln!("dump-failing: wrote {failing_total} failing indices to {}", p.display());
+        }
+    }
+    if let Some(p) = &dump_worst {
+        match argmin {
+            Some(idx) => {
+                let mut w = std::io::BufWriter::new(File::create(p)?);
+                write_trace_csv(&mut w, k, l, residues, idx)?;
+                w.flush()?;
+            }
+            None => eprintln!("dump-worst: min_S index not tracked by the resumed checkpoint; skipped"),
+        }
+    }
+
+    events.emit("complete", serde_json::json!({
+        "cmd": "gen", "min_s": min_s, "max_s": max_s, "mean_s": mean_s, "neg_drift_count": neg_drift, "threshold": thr, "pass": pass,
+        "eps": eps, "sha256_table_hex": hex(&digest),
+    }))?;
+    anstream::eprintln!("OK gen: {}", fields(&[kv("min_S", min_s), kv("thr", thr), kv_pass("pass", pass), kv("eps", format!("{eps:.6}"))]));
+    if let Some(n) = neg_drift {
+        eprintln!("  {}", fields(&[kv("eps<0", n), kv("frac(eps<0)", format!("{:.6}", n as f64 / count as f64))]));
+    }
     eprintln!("table.sha256={}", hex(&digest));
+    match manifest.peak_rss_bytes {
+        Some(b) => eprintln!("peak RSS: {:.1} MiB ({b} bytes)", b as f64 / (1u64 << 20) as f64),
+        None => eprintln!("peak RSS: unknown"),
+    }
+    if summary_line {
+        println!("{}", summary_line_of(&manifest, started.elapsed()));
+    }
+    if profile {
+        prof.report(profile_json.as_deref())?;
+    }
+
+    if let (true, Some(t), Some(m)) = (verify_after, &out_table, &out_manifest) {
+        verify(Some(k), Some(l), t.clone(), Some(m.clone()), nthreads, VerifyOpts { events, ..VerifyOpts::default() })?;
+    }
+    anyhow::ensure!(!require_pass || pass, "--require-pass: pass=false (min_S={min_s} < thr={thr})");
     Ok(())
 }
 
-fn verify(k: u32, l: u32, table_path: PathBuf, manifest_path: PathBuf, threads: usize) -> anyhow::Result<()> {
-    let nthreads = if threads == 0 {
-        std::thread::available_parallelism()?.get()
-    } else { threads };
-    eprintln!("threads={}", nthreads);
+/// `--summary-line`: one tab-separated record per run for appending to a log, taken from the
+/// written (gen) or checked (verify) manifest; the table sha256 is cut to 8 hex digits.
+fn summary_line_of(mf: &Manifest, elapsed: std::time::Duration) -> String {
+    format!(
+        "{}\t{}\t{}\t{}\t{}\t{}\t{:.6}\t{}\t{}",
+        mf.k, mf.l, mf.count, mf.min_s, mf.threshold, mf.pass, mf.eps, elapsed.as_millis(), &mf.sha256_table_hex[..8]
+    )
+}
 
-    let mut data = Vec::new();
-    File::open(&table_path)?.read_to_end(&mut data)?;
-    anyhow::ensure!(data.len() >= 64, "file too small");
-
-    anyhow::ensure!(&data[0..4] == b"CALT", "bad magic");
-    let ver = u32::from_le_bytes(data[4..8].try_into()?);
-    anyhow::ensure!(ver == 1 || ver == 2, "bad version");
-    let k_file = u32::from_le_bytes(data[8..12].try_into()?);
-    let l_file = u32::from_le_bytes(data[12..16].try_into()?);
-    let count_file = u64::from_le_bytes(data[16..24].try_into()?);
-    anyhow::ensure!(k == k_file && l == l_file, "K/L mismatch");
-    let count = count_file as usize;
-
-    let width: usize = if ver == 1 { 2 } else { 4 };
-    let need = 32 + count * width + 32;
-    anyhow::ensure!(data.len() == need, "bad file length");
-
-    let table_bytes = &data[32..(32 + count * width)];
-    let trailer = &data[(32 + count * width)..(32 + count * width + 32)];
-    let mut hasher = Sha256::new();
-    hasher.update(table_bytes);
-    let digest = hasher.finalize();
-    anyhow::ensure!(trailer == digest.as_slice(), "table sha256 mismatch");
+/// Peak resident set size of this process so far: VmHWM from /proc on Linux, getrusage
+/// elsewhere on unix (ru_maxrss is bytes on macOS, KiB on the BSDs); `None` otherwise.
+fn peak_rss_bytes() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let kb = status.lines().find_map(|line| line.strip_prefix("VmHWM:"))?;
+        kb.trim().strip_suffix("kB")?.trim().parse::<u64>().ok().map(|kb| kb * 1024)
+    }
+    #[cfg(all(unix, not(target_os = "linux")))]
+    {
+        let mut usage = std::mem::MaybeUninit::<libc::rusage>::uninit();
+        if unsafe { libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) } != 0 {
+            return None;
+        }
+        let max = unsafe { usage.assume_init() }.ru_maxrss as u64;
+        Some(if cfg!(target_os = "macos") { max } else { max * 1024 })
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+/// Error returned when a run stops on SIGINT/SIGTERM; `main` exits with status 130 for it.
+#[derive(Debug)]
+struct Interrupted;
+
+impl std::fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("interrupted")
+    }
+}
 
-    // parse table
-    let mut table: Vec<u32> = Vec::with_capacity(count);
-    if ver == 1 {
-        for i in 0..count {
-            let lo = table_bytes[2 * i] as u16;
-            let hi = (table_bytes[2 * i + 1] as u16) << 8;
-            table.push((lo | hi) as u32);
+impl std::error::Error for Interrupted {}
+
+/// SIGINT/SIGTERM latch polled between gen chunks (a no-op where there are no signals).
+mod interrupt {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+    #[cfg(unix)]
+    extern "C" fn on_signal(_: libc::c_int) {
+        REQUESTED.store(true, Ordering::Relaxed);
+    }
+
+    pub fn install() {
+        #[cfg(unix)]
+        unsafe {
+            let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            libc::signal(libc::SIGINT, handler);
+            libc::signal(libc::SIGTERM, handler);
         }
-    } else {
-        for i in 0..count {
-            let off = 4 * i;
-            let v = u32::from_le_bytes([
-                table_bytes[off],
-                table_bytes[off + 1],
-                table_bytes[off + 2],
-                table_bytes[off + 3],
-            ]);
-            table.push(v);
+    }
+
+    /// Back to the default action: past the chunk loop nothing polls the flag, so a signal
+    /// during hashing or --verify-after must terminate the process as usual.
+    pub fn restore() {
+        #[cfg(unix)]
+        unsafe {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+            libc::signal(libc::SIGTERM, libc::SIG_DFL);
         }
     }
 
-    let mask: u64 = (1u64 << k) - 1;
-    let recomputed_min = std::sync::atomic::AtomicU32::new(u32::MAX);
-    let ok = std::sync::atomic::AtomicBool::new(true);
+    pub fn requested() -> bool {
+        REQUESTED.load(Ordering::Relaxed)
+    }
+}
 
-    let pool = rayon::ThreadPoolBuilder::new().num_threads(nthreads).build()?;
-    pool.install(|| {
-        (0..count).into_par_iter().for_each(|idx| {
-            let mut m = ((idx as u64) << 1) | 1;
-            let mut s: u64 = 0;
-            for _ in 0..l {
-                let t = 3u64.wrapping_mul(m & mask).wrapping_add(1);
-                let e = t.trailing_zeros() as u64;
-                s += e;
-                m = (t >> e) & mask;
-            }
-            let s32 = s.min(u32::MAX as u64) as u32;
-            if s32 != table[idx] as u32 {
-                ok.store(false, std::sync::atomic::Ordering::Relaxed);
-            }
-            loop {
-                let cur = recomputed_min.load(std::sync::atomic::Ordering::Relaxed);
-                if s32 < cur {
-                    if recomputed_min.compare_exchange(
-                        cur, s32,
-                        std::sync::atomic::Ordering::Relaxed,
-                        std::sync::atomic::Ordering::Relaxed
-                    ).is_ok() { break; }
-                } else { break; }
-            }
-        });
-    });
+#[derive(Clone, Copy)]
+enum Phase { Compute, Hash, Io }
 
-    anyhow::ensure!(ok.load(std::sync::atomic::Ordering::Relaxed), "value mismatch");
-    let min_s = recomputed_min.load(std::sync::atomic::Ordering::Relaxed);
-    let thr = threshold_strict(l);
-    let pass = (min_s as u32) >= thr;
-    let eps = (min_s as f64) / (l as f64) - log2_3();
+/// Hashes and writes serialized chunks in index order: inline, or with `gen --pipeline` on a
+/// dedicated thread fed through a bounded channel, so hashing chunk N overlaps computing N+1.
+enum ChunkWriter {
+    Inline(TableSink, Sha256),
+    Thread {
+        tx: Option<std::sync::mpsc::SyncSender<Vec<u8>>>,
+        handle: Option<std::thread::JoinHandle<std::io::Result<(TableSink, Sha256)>>>,
+    },
+}
 
-    // check manifest
-    let mf: Manifest = serde_json::from_reader(File::open(&manifest_path)?)?;
-    anyhow::ensure!(mf.k == k && mf.l == l && mf.count as usize == count, "manifest mismatch");
-    anyhow::ensure!(mf.sha256_table_hex == hex(digest.as_slice()), "manifest sha256 mismatch");
-    if mf.file_ver != 0 { anyhow::ensure!(mf.file_ver == ver, "manifest file_ver mismatch"); }
-    // cross-check computed stats vs manifest
-    anyhow::ensure!(
-        mf.min_s == min_s,
-        "manifest min_s mismatch: manifest={} computed={}", mf.min_s, min_s
-    );
-    let thr2 = threshold_strict(mf.l);
-    anyhow::ensure!(
-        mf.threshold == thr2,
-        "manifest threshold mismatch: manifest={} expected={}", mf.threshold, thr2
-    );
-    anyhow::ensure!(
-        mf.pass == pass,
-        "manifest pass mismatch: manifest={} computed={}", mf.pass, pass
-    );
-    let eps2 = (min_s as f64) / (l as f64) - log2_3();
-    anyhow::ensure!(
-        (mf.eps - eps2).abs() < 1e-12,
-        "manifest eps mismatch: manifest={} computed={}", mf.eps, eps2
-    );
+type TableSink = Box<dyn Write + Send>;
 
-    eprintln!("verify: min_S={min_s} thr={thr} pass={pass} eps={:.6}", eps);
-    Ok(())
-}
+impl ChunkWriter {
+    fn new(mut f: TableSink, mut hasher: Sha256, pipeline: bool) -> ChunkWriter {
+        if !pipeline {
+            return ChunkWriter::Inline(f, hasher);
+        }
+        // one chunk in flight and one queued: memory stays at a few chunks
+        let (tx, rx) = std::sync::mpsc::sync_channel::<Vec<u8>>(1);
+        let handle = std::thread::spawn(move || {
+            for bytes in rx {
+                hasher.update(&bytes);
+                f.write_all(&bytes)?;
+            }
+            Ok((f, hasher))
+        });
+        ChunkWriter::Thread { tx: Some(tx), handle: Some(handle) }
+    }
 
-fn read_table_bytes(path: &Path) -> anyhow::Result<(u32,u32,u64,u32,Vec<u32>)> {
-    let mut data = Vec::new();
-    File::open(path)?.read_to_end(&mut data)?;
-    anyhow::ensure!(data.len() >= 64, "file too small");
-    anyhow::ensure!(&data[0..4] == b"CALT", "bad magic");
-    let ver = u32::from_le_bytes(data[4..8].try_into()?);
-    anyhow::ensure!(ver == 1 || ver == 2, "bad version");
-    let k_file = u32::from_le_bytes(data[8..12].try_into()?);
-    let l_file = u32::from_le_bytes(data[12..16].try_into()?);
-    let count_file = u64::from_le_bytes(data[16..24].try_into()?);
-    let count = count_file as usize;
-    let width: usize = if ver == 1 { 2 } else { 4 };
-    let need = 32 + count * width + 32;
-    anyhow::ensure!(data.len() == need, "bad file length");
-    let table_bytes = &data[32..(32 + count * width)];
-    let trailer = &data[(32 + count * width)..(32 + count * width + 32)];
-    let mut hasher = Sha256::new();
-    hasher.update(table_bytes);
-    let digest = hasher.finalize();
-    anyhow::ensure!(trailer == digest.as_slice(), "table sha256 mismatch");
-    let mut table: Vec<u32> = Vec::with_capacity(count);
-    if ver == 1 {
-        for i in 0..count {
-            let lo = table_bytes[2 * i] as u16;
-            let hi = (table_bytes[2 * i + 1] as u16) << 8;
-            table.push((lo | hi) as u32);
+    /// Hash and write `bytes`; the pipelined writer takes the buffer and leaves it empty.
+    fn put(&mut self, bytes: &mut Vec<u8>, prof: &mut Profile) -> anyhow::Result<()> {
+        match self {
+            ChunkWriter::Inline(f, hasher) => {
+                hasher.update(&*bytes);
+                prof.lap(Some(Phase::Hash));
+                f.write_all(bytes)?;
+                prof.lap(Some(Phase::Io));
+            }
+            ChunkWriter::Thread { tx, handle } => {
+                let next = Vec::with_capacity(bytes.capacity());
+                let sent = tx.as_ref().expect("writer open").send(std::mem::replace(bytes, next));
+                if sent.is_err() {
+                    // the writer only hangs up after a write error; surface that error
+                    tx.take();
+                    let r = handle.take().expect("writer running").join().expect("writer thread panicked");
+                    r?;
+                    anyhow::bail!("table writer stopped early");
+                }
+            }
         }
-    } else {
-        for i in 0..count {
-            let off = 4 * i;
-            let v = u32::from_le_bytes([
-                table_bytes[off], table_bytes[off+1], table_bytes[off+2], table_bytes[off+3]
-            ]);
-            table.push(v);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            ChunkWriter::Inline(f, _) => f.flush(),
+            // gen --pipeline conflicts with --checkpoint, the only caller
+            ChunkWriter::Thread { .. } => Err(std::io::Error::other("flush while pipelined")),
+        }
+    }
+
+    /// Wait for queued chunks and hand back the sink and the running hash.
+    fn finish(self) -> anyhow::Result<(TableSink, Sha256)> {
+        match self {
+            ChunkWriter::Inline(f, hasher) => Ok((f, hasher)),
+            ChunkWriter::Thread { mut tx, mut handle } => {
+                tx.take();
+                let h = handle.take().ok_or_else(|| anyhow::anyhow!("table writer stopped early"))?;
+                Ok(h.join().expect("writer thread panicked")?)
+            }
         }
     }
-    Ok((k_file, l_file, count_file, ver, table))
 }
 
-fn stats(table_path: PathBuf, bins: usize, out_csv: Option<PathBuf>) -> anyhow::Result<()> {
-    let (k, l, count_u64, ver, table) = read_table_bytes(&table_path)?;
-    let count = count_u64 as usize;
-    anyhow::ensure!(count > 0, "empty table");
-    let mut mn = u32::MAX; let mut mx = 0u32; let mut sum: f64 = 0.0;
-    for &v in &table { mn = min(mn, v); mx = max(mx, v); sum += v as f64; }
-    let mean = sum / (count as f64);
-    let thr = threshold_strict(l);
-    let eps = (mn as f64) / (l as f64) - log2_3();
-    // histogram
-    let bins = bins.max(1);
-    let lo = mn as i64; let hi = mx.max(mn+1) as i64; // avoid zero width
-    let width = (hi - lo) as f64 / (bins as f64);
-    let mut hist = vec![0usize; bins];
-    for &v in &table {
-        let idx = (((v as i64 - lo) as f64) / width).floor() as isize;
-        let idx = idx.clamp(0, (bins as isize)-1) as usize;
-        hist[idx] += 1;
+/// `gen --profile`: wall time per phase, charged at each `lap` to the phase just finished.
+/// Time outside any phase (manifest, exe hash, throttle pauses) is reported as `other`.
+struct Profile {
+    on: bool,
+    start: std::time::Instant,
+    last: std::time::Instant,
+    spent: [std::time::Duration; 3],
+}
+
+impl Profile {
+    fn new(on: bool) -> Profile {
+        let now = std::time::Instant::now();
+        Profile { on, start: now, last: now, spent: Default::default() }
     }
-    eprintln!("stats: K={k} L={l} ver={ver} count={count}");
-    eprintln!("  min_S={mn} max_S={mx} mean={:.3}", mean);
-    eprintln!("  thr={thr} pass(min)={}" , (mn as u32) >= thr);
-    eprintln!("  eps(min)={:.6}", eps);
-    if let Some(csv) = out_csv {
-        let mut w = std::io::BufWriter::new(File::create(csv)?);
-        writeln!(w, "bin_lo,bin_hi,count")?;
-        for i in 0..bins {
-            let b_lo = lo as f64 + (i as f64)*width;
-            let b_hi = lo as f64 + ((i+1) as f64)*width;
-            writeln!(w, "{:.6},{:.6},{}", b_lo, b_hi, hist[i])?;
+
+    /// Close the current interval, charging it to `phase` (None discards it).
+    #[inline]
+    fn lap(&mut self, phase: Option<Phase>) {
+        if !self.on { return; }
+        let now = std::time::Instant::now();
+        if let Some(p) = phase {
+            self.spent[p as usize] += now - self.last;
         }
+        self.last = now;
     }
-    Ok(())
-}
 
-fn pack(table_path: PathBuf, manifest_path: PathBuf, out: Option<PathBuf>, checksums: bool) -> anyhow::Result<()> {
-    // verify and extract header fields
-    let (k, l, _count, ver, _table) = read_table_bytes(&table_path)?;
-    // default out name
-    let out_path = out.unwrap_or_else(|| PathBuf::from(format!("cert_k{}_l{}_v{}.tar.gz", k, l, ver)));
-    let tar_gz = File::create(&out_path)?;
-    let enc = flate2::write::GzEncoder::new(tar_gz, flate2::Compression::default());
-    let mut tarb = tar::Builder::new(enc);
-    // add files with just their basenames
-    let table_name = table_path.file_name().unwrap_or_else(|| std::ffi::OsStr::new("table.bin"));
-    let manifest_name = manifest_path.file_name().unwrap_or_else(|| std::ffi::OsStr::new("manifest.json"));
-    tarb.append_path_with_name(&table_path, table_name)?;
-    tarb.append_path_with_name(&manifest_path, manifest_name)?;
-    let enc = tarb.into_inner()?; // GzEncoder
-    let mut inner = enc.finish()?; // File
-    inner.flush()?;
-    // compute sha256 of archive
-    let sha = sha256_file(&out_path)?;
-    println!("tar.gz sha256={} file={}", sha, out_path.display());
-    if checksums {
-        let mut f = File::create("CHECKSUMS.sha256")?;
-        writeln!(f, "{}  {}", sha, out_path.file_name().unwrap().to_string_lossy())?;
+    fn report(&self, json: Option<&Path>) -> anyhow::Result<()> {
+        let total = self.start.elapsed().as_secs_f64();
+        let [compute, hash, io] = self.spent.map(|d| d.as_secs_f64());
+        let other = (total - compute - hash - io).max(0.0);
+        let secs = |v: f64| format!("{v:.3}s");
+        eprintln!("profile: {}", fields(&[kv("compute", secs(compute)), kv("hash", secs(hash)), kv("io", secs(io)),
+                                          kv("other", secs(other)), kv("total", secs(total))]));
+        if let Some(p) = json {
+            let v = serde_json::json!({
+                "compute_s": compute, "hash_s": hash, "io_s": io, "other_s": other, "total_s": total,
+            });
+            std::fs::write(p, serde_json::to_string_pretty(&v)? + "\n").map_err(path_io_error(p))?;
+        }
+        Ok(())
     }
-    Ok(())
 }
 
-#[inline]
-fn log2_3() -> f64 { 3f64.log2() }
+/// gen's running totals over the entries `[0, next)` written so far. `argmin` is the lowest
+/// index attaining min_s; it, max_s, the exact sum and the negative-drift count are None
+/// when unknown after resuming an older checkpoint.
+struct Progress {
+    next: u64,
+    min_s: u32,
+    argmin: Option<u64>,
+    max_s: Option<u32>,
+    sum_s: Option<u64>,
+    neg_drift: Option<u64>,
+}
 
-#[inline]
-fn threshold_strict(l: u32) -> u32 {
-    ((l as f64)*log2_3()).floor() as u32 + 1
+impl Progress {
+    const FRESH: Progress = Progress { next: 0, min_s: u32::MAX, argmin: None, max_s: Some(0), sum_s: Some(0), neg_drift: Some(0) };
 }
 
-fn hex(bytes: &[u8]) -> String {
-    let mut s = String::with_capacity(bytes.len() * 2);
-    for b in bytes {
-        s.push_str(&format!("{:02x}", b));
-    }
-    s
+/// A partly written table reopened from its checkpoint, positioned for appending.
+struct GenState {
+    file: File,
+    hasher: Sha256,
+    progress: Progress,
 }
 
-fn sha256_file(p: &Path) -> anyhow::Result<String> {
-    let f = File::open(p)?;
-    let mut r = BufReader::new(f);
+/// Reopen a partially written table described by `cp_path`. The body is truncated to the
+/// checkpointed index and rehashed so the final trailer covers the complete body.
+/// Returns None (fresh start) if there is no usable checkpoint for these parameters. An
+/// older checkpoint without `log_constant` resumes without its negative-drift count.
+fn resume_checkpoint(cp_path: &Path, header: &Header, out_table: &Path, log_constant: f64) -> anyhow::Result<Option<GenState>> {
+    if !cp_path.exists() { return Ok(None); }
+    let cp: Checkpoint = serde_json::from_reader(File::open(cp_path)?)?;
+    let include_even = header.residues() == Residues::All;
+    if cp.k != header.k || cp.l != header.l || cp.file_ver != header.ver
+        || cp.log_constant.is_some_and(|c| c != log_constant) || cp.include_even.is_some_and(|e| e != include_even)
+        || cp.table != out_table.display().to_string() || cp.next_index > header.count {
+        eprintln!("checkpoint: parameters differ, starting fresh");
+        return Ok(None);
+    }
+    let body_len = cp.next_index * header.width() as u64;
+    let mut f = match std::fs::OpenOptions::new().read(true).write(true).open(out_table) {
+        Ok(f) => f,
+        Err(_) => {
+            eprintln!("checkpoint: table file missing, starting fresh");
+            return Ok(None);
+        }
+    };
+    let mut hdr = [0u8; 32];
+    let mut expect = Vec::with_capacity(32);
+    write_header(&mut expect, header)?;
+    if f.metadata()?.len() < 32 + body_len || f.read_exact(&mut hdr).is_err() || hdr[..] != expect[..] {
+        eprintln!("checkpoint: table file does not match, starting fresh");
+        return Ok(None);
+    }
+    f.set_len(32 + body_len)?;
     let mut hasher = Sha256::new();
+    let mut r = BufReader::new(&mut f);
     let mut buf = [0u8; 8192];
     loop {
         let n = r.read(&mut buf)?;
         if n == 0 { break; }
         hasher.update(&buf[..n]);
     }
-    Ok(hex(&hasher.finalize()))
+    drop(r);
+    f.seek(std::io::SeekFrom::End(0))?;
+    eprintln!("checkpoint: resuming at index {} of {}", cp.next_index, header.count);
+    Ok(Some(GenState {
+        file: f,
+        hasher,
+        progress: Progress {
+            next: cp.next_index,
+            min_s: cp.min_s,
+            argmin: cp.min_s_index,
+            max_s: cp.max_s,
+            sum_s: cp.sum_s,
+            neg_drift: cp.neg_drift_count.filter(|_| cp.log_constant.is_some()),
+        },
+    }))
+}
+
+/// Atomically replace the checkpoint sidecar (write to a temp file, then rename).
+fn write_checkpoint(path: &Path, cp: &Checkpoint) -> anyhow::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let mut f = File::create(&tmp)?;
+    serde_json::to_writer(&mut f, cp)?;
+    f.sync_data()?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Write the trajectory of table entry `idx` as CSV, one row per step (see `trace`).
+fn write_trace_csv(w: &mut impl Write, k: u32, l: u32, residues: Residues, idx: u64) -> anyhow::Result<()> {
+    let mask: u64 = (1u64 << k) - 1;
+    let rows = trace(residues, mask, l, idx);
+    let residue = match residues { Residues::Odd => (idx << 1) | 1, Residues::All => idx };
+    writeln!(w, "# k={k} l={l} index={idx} residue={residue} s={}", rows.last().map_or(0, |r| r.s))?;
+    writeln!(w, "step,m,e,s,next")?;
+    for r in &rows {
+        writeln!(w, "{},{},{},{},{}", r.step, r.m, r.e, r.s, r.next)?;
+    }
+    Ok(())
+}
+
+/// `trace`: one entry's trajectory, in full or (`--summary`) as a few figures. Drops and
+/// the largest exponent count the l odd steps only, not the leading halvings (step 0) of
+/// an even residue, which are still part of S.
+fn trace_cmd(k: u32, l: u32, residues: Residues, idx: u64, out: Option<&Path>, summary: bool) -> anyhow::Result<()> {
+    anyhow::ensure!((2..=28).contains(&k), "k in [2,28]");
+    let count = residues.count(k);
+    anyhow::ensure!(idx < count, "--index {idx} out of range: k={k} has {count} entries");
+    if summary {
+        let rows = trace(residues, (1u64 << k) - 1, l, idx);
+        let steps = rows.iter().filter(|r| r.step > 0);
+        let drops = steps.clone().filter(|r| r.e >= 2).count();
+        let max_e = steps.map(|r| r.e).max().unwrap_or(0);
+        let residue = match residues { Residues::Odd => (idx << 1) | 1, Residues::All => idx };
+        let last = rows.last().map_or(residue, |r| r.next);
+        println!("{}", fields(&[kv("K", k), kv("L", l), kv("index", idx), kv("residue", residue)]));
+        println!("{}", fields(&[kv("S", rows.last().map_or(0, |r| r.s)), kv("e>=2", drops), kv("max_e", max_e), kv("final", last)]));
+        return Ok(());
+    }
+    match out {
+        Some(p) => {
+            let mut w = std::io::BufWriter::new(File::create(p).map_err(path_io_error(p))?);
+            write_trace_csv(&mut w, k, l, residues, idx)?;
+            w.flush()?;
+        }
+        None => write_trace_csv(&mut std::io::stdout().lock(), k, l, residues, idx)?,
+    }
+    Ok(())
+}
+
+/// Manifest paired with a table by the gen naming convention: `table_<rest>.bin[.gz]` goes
+/// with `cert_<rest>.json`.
+fn cert_for_table(name: &str) -> Option<String> {
+    let rest = name.strip_prefix("table_")?;
+    let rest = rest.strip_suffix(".bin.gz").or_else(|| rest.strip_suffix(".bin"))?;
+    Some(format!("cert_{rest}.json"))
+}
+
+/// Shell-style match of a whole file name: `*` is any run of characters, `?` any one.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let (p, n): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    // greedy with backtracking to the last `*`
+    let (mut i, mut j, mut star) = (0, 0, None);
+    while j < n.len() {
+        if i < p.len() && (p[i] == '?' || p[i] == n[j]) {
+            i += 1;
+            j += 1;
+        } else if i < p.len() && p[i] == '*' {
+            star = Some((i, j));
+            i += 1;
+        } else if let Some((si, sj)) = star {
+            i = si + 1;
+            j = sj + 1;
+            star = Some((si, sj + 1));
+        } else {
+            return false;
+        }
+    }
+    p[i..].iter().all(|&c| c == '*')
+}
+
+/// `verify-all`: a full verify of each table/manifest pair in `dir`, `jobs` at a time with
+/// the thread budget split between them so the nested rayon pools do not oversubscribe.
+/// The verdict for each is the manifest's (now checked) pass flag.
+fn verify_all(dir: &Path, filter: Option<&str>, jobs: Option<usize>, threads: usize) -> anyhow::Result<()> {
+    let mut tables: Vec<String> = std::fs::read_dir(dir)
+        .map_err(path_io_error(dir))?
+        .filter_map(|e| e.ok()?.file_name().into_string().ok())
+        .filter(|name| cert_for_table(name).is_some() && filter.is_none_or(|f| wildcard_match(f, name)))
+        .collect();
+    tables.sort();
+    anyhow::ensure!(!tables.is_empty(), "verify-all: no table_*.bin files{} in {}",
+        filter.map_or(String::new(), |f| format!(" matching {f:?}")), dir.display());
+    let total = if threads == 0 { std::thread::available_parallelism()?.get() } else { threads };
+    let jobs = jobs.unwrap_or(total).clamp(1, tables.len());
+    let per_job = (total / jobs).max(1);
+    eprintln!("verify-all: {} certificate(s) in {}, jobs={jobs} threads/job={per_job}", tables.len(), dir.display());
+
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
+    let results: Vec<anyhow::Result<(Manifest, Verdict)>> = pool.install(|| {
+        tables.par_iter().map(|name| {
+            let cert = dir.join(cert_for_table(name).expect("filtered above"));
+            anyhow::ensure!(cert.exists(), "missing {}", cert.display());
+            let verdict = verify(None, None, dir.join(name), Some(cert.clone()), per_job, VerifyOpts::default())?;
+            Ok((serde_json::from_reader(File::open(&cert)?)?, verdict))
+        }).collect()
+    });
+
+    println!("verify-all: {}", dir.display());
+    let mut failed = 0;
+    for (name, r) in tables.iter().zip(&results) {
+        match r {
+            Ok((mf, Verdict::Full { pass })) => {
+                failed += !pass as usize;
+                anstream::println!("  {}", fields(&[kv("k", mf.k), kv("l", mf.l), kv_pass("pass", *pass), name.clone()]));
+            }
+            Ok((mf, Verdict::NotCertified)) => {
+                failed += 1;
+                anstream::println!("  {}", fields(&[kv("k", mf.k), kv("l", mf.l), kv_pass("pass", false), format!("{name}: partial table, not certified")]));
+            }
+            Err(e) => {
+                failed += 1;
+                anstream::println!("  {}", fields(&[kv("k", "?"), kv("l", "?"), kv_pass("pass", false), format!("{name}: {e}")]));
+            }
+        }
+    }
+    println!("  {} checked, {failed} failed", tables.len());
+    anyhow::ensure!(failed == 0, "verify-all: {failed} of {} certificate(s) failed", tables.len());
+    Ok(())
+}
+
+/// Run `gen` for each (k, l) pair; with `skip_existing`, pairs whose files are present
+/// and pass a full verify are left alone.
+fn sweep(ks: &[u32], ls: &[u32], threads: usize, out_dir: &Path, skip_existing: bool) -> anyhow::Result<()> {
+    std::fs::create_dir_all(out_dir)?;
+    let (mut skipped, mut generated, mut repaired) = (0usize, 0usize, 0usize);
+    for &k in ks {
+        for &l in ls {
+            let table = out_dir.join(default_table_name(k, l));
+            let manifest = out_dir.join(default_manifest_name(k, l));
+            if skip_existing && table.exists() && manifest.exists() {
+                match verify(Some(k), Some(l), table.clone(), Some(manifest.clone()), threads, VerifyOpts::default()) {
+                    Ok(Verdict::Full { .. }) => {
+                        eprintln!("sweep k={k} l={l}: verified, skipped");
+                        skipped += 1;
+                        continue;
+                    }
+                    Ok(Verdict::NotCertified) => {
+                        eprintln!("sweep k={k} l={l}: existing table is partial; regenerating");
+                        repaired += 1;
+                    }
+                    Err(e) => {
+                        eprintln!("sweep k={k} l={l}: existing files fail verify ({e}); regenerating");
+                        repaired += 1;
+                    }
+                }
+            }
+            gen(k, l, threads, GenOpts {
+                out_table: Some(table),
+                out_manifest: Some(manifest),
+                checkpoint: None,
+                residues: Residues::Odd,
+                metrics: None,
+                manifest_stdout: false,
+                table_stdout: false,
+                log_constant: log2_3(),
+                note: None,
+                verify_after: false,
+                require_pass: false,
+                throttle_eps: None,
+                profile: false,
+                profile_json: None,
+                with_state: false,
+                extend_from: None,
+                dump_worst: None,
+                dump_failing: None,
+                events: Events::default(),
+                pipeline: false,
+                summary_line: false,
+                max_index: None,
+            })?;
+            generated += 1;
+        }
+    }
+    eprintln!("sweep: {} pairs: {skipped} skipped, {generated} generated ({repaired} after failed verify)", skipped + generated);
+    Ok(())
+}
+
+/// What every chunk of one run shares: the residue set and walk length, and the bound
+/// below which an S value counts as negative drift (0 counts nothing).
+#[derive(Clone, Copy)]
+struct Walk {
+    mask: u64,
+    l: u32,
+    residues: Residues,
+    neg_bound: u64,
+}
+
+/// Fill `chunk` with S values for indices `start..start+chunk.len()` in parallel on the
+/// current rayon pool; returns the chunk summary.
+fn compute_chunk(chunk: &mut [u32], start: u64, walk: Walk) -> Summary {
+    let Walk { mask, l, residues, neg_bound } = walk;
+    chunk.par_chunks_mut(4096).enumerate().map(|(ci, sub)| {
+        let base = start + (ci * 4096) as u64;
+        let mut sub_sum = Summary::EMPTY;
+        for (j, slot) in sub.iter_mut().enumerate() {
+            *slot = residues.s_sum(mask, l, base + j as u64);
+            sub_sum.add(base + j as u64, *slot, neg_bound);
+        }
+        sub_sum
+    }).reduce(|| Summary::EMPTY, Summary::merge)
+}
+
+/// Min (with the lowest index attaining it), max, exact sum and negative-drift count of S
+/// over some entries.
+#[derive(Clone, Copy)]
+struct Summary {
+    min: u32,
+    argmin: u64,
+    max: u32,
+    sum: u64,
+    neg: u64,
+}
+
+impl Summary {
+    const EMPTY: Summary = Summary { min: u32::MAX, argmin: u64::MAX, max: 0, sum: 0, neg: 0 };
+
+    /// Fold in entry `idx`, counting it as negative drift when `s < neg_bound`; entries must
+    /// arrive in increasing index order.
+    #[inline]
+    fn add(&mut self, idx: u64, s: u32, neg_bound: u64) {
+        if s < self.min {
+            self.min = s;
+            self.argmin = idx;
+        }
+        self.max = max(self.max, s);
+        self.sum += s as u64;
+        self.neg += u64::from((s as u64) < neg_bound);
+    }
+
+    fn merge(self, o: Summary) -> Summary {
+        let (min, argmin) = min((self.min, self.argmin), (o.min, o.argmin));
+        Summary { min, argmin, max: max(self.max, o.max), sum: self.sum + o.sum, neg: self.neg + o.neg }
+    }
+}
+
+/// `compute_chunk` for ver=3 tables, also filling each residue's end state. With `from`
+/// = (l0, entries of an l0 table) each trajectory continues from the stored state for the
+/// remaining `l - l0` steps instead of starting over.
+fn compute_chunk_state(chunk: &mut [u32], ends: &mut [u32], start: u64, walk: Walk,
+                       from: Option<(u32, &[(u32, u32)])>) -> Summary {
+    let Walk { mask, l, residues, neg_bound } = walk;
+    chunk.par_chunks_mut(4096).zip(ends.par_chunks_mut(4096)).enumerate().map(|(ci, (sub, sub_ends))| {
+        let base = start + (ci * 4096) as u64;
+        let mut sub_sum = Summary::EMPTY;
+        for (j, (slot, end)) in sub.iter_mut().zip(sub_ends.iter_mut()).enumerate() {
+            let (s, m) = match from {
+                Some((l0, prev)) => {
+                    let (s0, m0) = prev[ci * 4096 + j];
+                    let (ds, m) = steps(mask, l - l0, m0 as u64);
+                    (s0.saturating_add(ds), m)
+                }
+                None => residues.s_sum_state(mask, l, base + j as u64),
+            };
+            *slot = s;
+            *end = m as u32;
+            sub_sum.add(base + j as u64, s, neg_bound);
+        }
+        sub_sum
+    }).reduce(|| Summary::EMPTY, Summary::merge)
+}
+
+/// Next `(s, m)` entry of an `--extend-from` source.
+fn read_base_entry(r: &mut TableReader) -> anyhow::Result<(u32, u32)> {
+    match r.next_entry() {
+        Some(Ok((_, s, Some(m)))) => Ok((s, m)),
+        Some(Err(e)) => Err(e),
+        _ => anyhow::bail!("--extend-from: table ended early"),
+    }
+}
+
+fn bench(k: u32, l: u32, iters: usize, threads: usize, start_index: u64) -> anyhow::Result<()> {
+    anyhow::ensure!((2..=28).contains(&k), "k in [2,28]");
+    anyhow::ensure!(l >= 1, "l >= 1");
+    anyhow::ensure!(iters >= 1, "iters >= 1");
+    let count: u64 = 1u64 << (k - 1);
+    anyhow::ensure!(start_index < count, "--start-index {start_index} out of range: k={k} has {count} entries");
+    let nthreads = if threads == 0 {
+        std::thread::available_parallelism()?.get()
+    } else { threads };
+    eprintln!("threads={}", nthreads);
+
+    let walk = Walk { mask: (1u64 << k) - 1, l, residues: Residues::Odd, neg_bound: 0 };
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(nthreads).build()?;
+    let mut chunk: Vec<u32> = vec![0; min(GEN_CHUNK as u64, count) as usize];
+    // one full pass over the same chunked compute path as gen, without file IO; from
+    // --start-index to the end, then from 0 (a chunk is cut short at the wrap)
+    let mut pass = || {
+        let mut min_s = u32::MAX;
+        let mut done = 0u64;
+        while done < count {
+            let start = (start_index + done) % count;
+            let len = min(GEN_CHUNK as u64, min(count - done, count - start)) as usize;
+            min_s = min(min_s, pool.install(|| compute_chunk(&mut chunk[..len], start, walk)).min);
+            done += len as u64;
+        }
+        min_s
+    };
+
+    let min_s = pass(); // warm-up
+    let mut times: Vec<f64> = (0..iters).map(|_| {
+        let t0 = std::time::Instant::now();
+        std::hint::black_box(pass());
+        t0.elapsed().as_secs_f64()
+    }).collect();
+    times.sort_by(|a, b| a.total_cmp(b));
+    let median = if iters % 2 == 1 {
+        times[iters / 2]
+    } else {
+        (times[iters / 2 - 1] + times[iters / 2]) / 2.0
+    };
+    let rps = count as f64 / median;
+    let ns_step = median * 1e9 / (count as f64 * l as f64);
+    eprintln!("bench: K={k} L={l} count={count} iters={iters} min_S={min_s}");
+    if start_index > 0 {
+        eprintln!("  start_index={start_index} (wraps to 0)");
+    }
+    eprintln!("  median={:.6}s residues/s={:.0} ns/step={:.3}", median, rps, ns_step);
+    Ok(())
+}
+
+/// Copy-pasteable gen command for a table: only the parameters that affect its contents.
+fn reproduce_cmd(k: u32, l: u32, residues: Residues, with_state: bool, max_index: Option<u64>) -> String {
+    let mut cmd = format!("collatz_cert gen --k {k} --l {l}");
+    if residues == Residues::All { cmd.push_str(" --include-even"); }
+    if with_state { cmd.push_str(" --with-state"); }
+    if let Some(n) = max_index { cmd.push_str(&format!(" --max-index {n}")); }
+    cmd
+}
+
+fn default_table_name(k: u32, l: u32) -> String { format!("table_k{}_l{}_v2.bin", k, l) }
+
+fn default_manifest_name(k: u32, l: u32) -> String { format!("cert_k{}_l{}_v2.json", k, l) }
+
+/// Optional verify modes; the default is a full recompute of every residue.
+struct VerifyOpts {
+    index_range: Option<(u64, u64)>,
+    sample: Option<u64>,
+    seed: u64,
+    diagnose: bool,
+    metrics: Option<PathBuf>,
+    first_fail: bool,
+    events: Events,
+    /// Read the table bypassing the page cache
+    direct: bool,
+    /// Print the step-by-step reasoning after a full verify
+    explain: bool,
+    /// Accepted |eps| difference against the manifest; 0 means bitwise equal
+    eps_tolerance: f64,
+    /// Recompute this many times and require identical results
+    repeat: usize,
+    summary_line: bool,
+    /// Known-good min_s that the full recompute must reproduce
+    expect_min_s: Option<u32>,
+    /// Read-stage threads (0 = all cores); `None` uses the recompute thread count
+    io_threads: Option<usize>,
+    /// Sequential read-ahead hint on the table file
+    prefetch: bool,
+    /// Least acceptable min_s - threshold
+    min_margin: Option<i64>,
+    /// Where to write a manifest rebuilt from the recompute, once everything has passed
+    rewrite_manifest: Option<PathBuf>,
+}
+
+impl Default for VerifyOpts {
+    fn default() -> VerifyOpts {
+        VerifyOpts {
+            index_range: None,
+            sample: None,
+            seed: 0,
+            diagnose: false,
+            metrics: None,
+            first_fail: false,
+            events: Events::default(),
+            direct: false,
+            explain: false,
+            eps_tolerance: EPS_TOLERANCE,
+            repeat: 1,
+            summary_line: false,
+            expect_min_s: None,
+            io_threads: None,
+            prefetch: false,
+            min_margin: None,
+            rewrite_manifest: None,
+        }
+    }
+}
+
+/// Indices per work item in verify's recompute; the --first-fail stop flag is polled
+/// once per chunk.
+const VERIFY_CHUNK: usize = 4096;
+
+fn parse_index_range(s: &str) -> Result<(u64, u64), String> {
+    let (a, b) = s.split_once(':').ok_or("expected A:B")?;
+    let a: u64 = a.trim().parse().map_err(|e| format!("bad range start: {e}"))?;
+    let b: u64 = b.trim().parse().map_err(|e| format!("bad range end: {e}"))?;
+    if a >= b { return Err(format!("empty range {a}:{b}")); }
+    Ok((a, b))
+}
+
+/// What a `verify` that returned `Ok` established about the table.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Verdict {
+    /// Every residue was recomputed: min_S and with it the pass verdict are established
+    Full { pass: bool },
+    /// The stored entries check out, but they do not cover every residue (--index-range,
+    /// --sample, or a table flagged partial), so nothing is certified
+    NotCertified,
+}
+
+/// The verify read stage is one sha256 pass joined with one body decode, so an IO pool
+/// larger than this would only have idle threads.
+const IO_STAGE_TASKS: usize = 2;
+
+fn verify(k: Option<u32>, l: Option<u32>, table_path: PathBuf, manifest_path: Option<PathBuf>, threads: usize, opts: VerifyOpts) -> anyhow::Result<Verdict> {
+    let resolve = |t: usize| -> anyhow::Result<usize> {
+        Ok(if t == 0 { std::thread::available_parallelism()?.get() } else { t })
+    };
+    let nthreads = resolve(threads)?;
+    let io_threads = min(resolve(opts.io_threads.unwrap_or(threads))?, IO_STAGE_TASKS);
+    eprintln!("threads: io={io_threads} recompute={nthreads}");
+    let started = std::time::Instant::now();
+
+    let data = if opts.direct {
+        let (data, direct) = read_table_file_direct(&table_path)?;
+        if !direct {
+            eprintln!("direct: not supported for {}; read it buffered", table_path.display());
+        }
+        data
+    } else if opts.prefetch {
+        let (data, applied) = read_table_file_prefetch(&table_path)?;
+        note_prefetch(&table_path, applied);
+        data
+    } else {
+        read_table_file(&table_path)?
+    };
+    let header = Header::parse(&data)?;
+    let (k_file, l_file) = (header.k, header.l);
+    opts.events.emit("start", serde_json::json!({
+        "cmd": "verify", "k": k_file, "l": l_file, "count": header.count, "threads": nthreads, "io_threads": io_threads,
+        "table": table_path.display().to_string(),
+    }))?;
+    anyhow::ensure!(
+        k.unwrap_or(k_file) == k_file && l.unwrap_or(l_file) == l_file,
+        "K/L mismatch: file has k={k_file} l={l_file}"
+    );
+    anyhow::ensure!(
+        header.algo_compatible(),
+        "table algo_ver={} is incompatible with this build (algo_ver={ALGO_VER}): its S values follow another definition",
+        header.algo_ver
+    );
+    let (k, l, ver) = (k_file, l_file, header.ver);
+    let residues = header.residues();
+    let count = header.count as usize;
+    anyhow::ensure!(
+        !header.partial()
+            || (opts.min_margin.is_none() && opts.rewrite_manifest.is_none() && opts.metrics.is_none() && !opts.summary_line && !opts.explain),
+        "{} is a partial table (count={count} of {}): --min-margin, --rewrite-manifest, --metrics, --summary-line and --explain need a full one",
+        table_path.display(), residues.count(k)
+    );
+
+    let width: usize = header.width();
+    // ver=4 bodies have a data-dependent length: everything between header and trailer
+    let body_len = match header.body_len() {
+        Some(n) => {
+            anyhow::ensure!(data.len() == 32 + n + 32, "bad file length");
+            n
+        }
+        None => data.len() - 64,
+    };
+
+    let table_bytes = &data[32..32 + body_len];
+    let trailer = &data[32 + body_len..];
+    // the read stage: hash the body while decoding it; a bad digest wins over a decode error
+    let io_pool = rayon::ThreadPoolBuilder::new().num_threads(io_threads).build()?;
+    let (digest, decoded) = io_pool.install(|| rayon::join(
+        || Sha256::digest(table_bytes),
+        || decode_body(table_bytes, ver, count),
+    ));
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(nthreads).build()?;
+    if trailer != digest.as_slice() {
+        if opts.diagnose && ver != VER_RLE {
+            let report = pool.install(|| diagnose_mismatch(table_bytes, width, k, l, residues));
+            anyhow::bail!("table sha256 mismatch: {report}");
+        }
+        anyhow::bail!("table sha256 mismatch");
+    }
+    // ver=3 entries also carry the end residue
+    let (table, ends) = decoded?;
+
+    let (lo, hi) = opts.index_range.unwrap_or((0, count as u64));
+    anyhow::ensure!(hi <= count as u64, "index range {lo}:{hi} exceeds count={count}");
+    // --sample: indices drawn with replacement from a seeded SplitMix64 stream
+    let sample: Option<Vec<usize>> = opts.sample.map(|n| {
+        let mut rng = SplitMix64(opts.seed);
+        (0..n).map(|_| rng.below(count as u64) as usize).collect()
+    });
+    let partial = sample.is_some() || (lo, hi) != (0, count as u64);
+
+    let mask: u64 = (1u64 << k) - 1;
+    let recomputed_min = std::sync::atomic::AtomicU32::new(u32::MAX);
+    let recomputed_max = std::sync::atomic::AtomicU32::new(0);
+    let recomputed_sum = std::sync::atomic::AtomicU64::new(0);
+    let ok = std::sync::atomic::AtomicBool::new(true);
+
+    let stop = std::sync::atomic::AtomicBool::new(false);
+    // lowest mismatching index seen by any worker
+    let bad_idx = std::sync::atomic::AtomicUsize::new(usize::MAX);
+    let recompute = |idx: usize| -> (u32, Option<u32>) {
+        if ends.is_empty() {
+            (residues.s_sum(mask, l, idx as u64), None)
+        } else {
+            let (s, m) = residues.s_sum_state(mask, l, idx as u64);
+            (s, Some(m as u32))
+        }
+    };
+    let check = |idx: usize| {
+        let (s32, m) = recompute(idx);
+        if s32 != table[idx] || m.is_some_and(|m| m != ends[idx]) {
+            ok.store(false, std::sync::atomic::Ordering::Relaxed);
+            bad_idx.fetch_min(idx, std::sync::atomic::Ordering::Relaxed);
+            if opts.first_fail {
+                stop.store(true, std::sync::atomic::Ordering::Relaxed);
+            }
+        }
+        loop {
+            let cur = recomputed_min.load(std::sync::atomic::Ordering::Relaxed);
+            if s32 < cur {
+                if recomputed_min.compare_exchange(
+                    cur, s32,
+                    std::sync::atomic::Ordering::Relaxed,
+                    std::sync::atomic::Ordering::Relaxed
+                ).is_ok() { break; }
+            } else { break; }
+        }
+        s32
+    };
+    // max and sum are folded per chunk so the shared atomics are touched once per chunk
+    // progress events fire whenever the checked total crosses another whole percent
+    let total = sample.as_ref().map_or(hi - lo, |s| s.len() as u64).max(1);
+    let checked = std::sync::atomic::AtomicU64::new(0);
+    let tally = |(mx, sum): (u32, u64), n: usize| {
+        recomputed_max.fetch_max(mx, std::sync::atomic::Ordering::Relaxed);
+        recomputed_sum.fetch_add(sum, std::sync::atomic::Ordering::Relaxed);
+        let done = checked.fetch_add(n as u64, std::sync::atomic::Ordering::Relaxed) + n as u64;
+        if done * 100 / total > (done - n as u64) * 100 / total {
+            // best effort from worker threads; a failing sink surfaces at the complete event
+            let _ = opts.events.emit("progress", serde_json::json!({
+                "done": done, "count": total, "fraction": done as f64 / total as f64,
+                "min_s": recomputed_min.load(std::sync::atomic::Ordering::Relaxed),
+            }));
+        }
+    };
+    let fold = |acc: (u32, u64), s: u32| (max(acc.0, s), acc.1 + s as u64);
+    let stopped = || stop.load(std::sync::atomic::Ordering::Relaxed);
+    // --repeat: the same loaded table recomputed again from reset accumulators; every run
+    // must reach the same (min, max, sum, first mismatch)
+    let mut runs: Vec<(u32, u32, u64, usize)> = Vec::with_capacity(opts.repeat);
+    for _ in 0..opts.repeat.max(1) {
+        recomputed_min.store(u32::MAX, std::sync::atomic::Ordering::Relaxed);
+        recomputed_max.store(0, std::sync::atomic::Ordering::Relaxed);
+        recomputed_sum.store(0, std::sync::atomic::Ordering::Relaxed);
+        ok.store(true, std::sync::atomic::Ordering::Relaxed);
+        bad_idx.store(usize::MAX, std::sync::atomic::Ordering::Relaxed);
+        checked.store(0, std::sync::atomic::Ordering::Relaxed);
+        pool.install(|| match &sample {
+            Some(idxs) => idxs.par_chunks(VERIFY_CHUNK).for_each(|c| {
+                if !stopped() { tally(c.iter().map(|&idx| check(idx)).fold((0, 0), fold), c.len()); }
+            }),
+            None => (lo as usize..hi as usize).into_par_iter().step_by(VERIFY_CHUNK).for_each(|c| {
+                if !stopped() {
+                    let end = min(c + VERIFY_CHUNK, hi as usize);
+                    tally((c..end).map(check).fold((0, 0), fold), end - c);
+                }
+            }),
+        });
+        runs.push((
+            recomputed_min.load(std::sync::atomic::Ordering::Relaxed),
+            recomputed_max.load(std::sync::atomic::Ordering::Relaxed),
+            recomputed_sum.load(std::sync::atomic::Ordering::Relaxed),
+            bad_idx.load(std::sync::atomic::Ordering::Relaxed),
+        ));
+    }
+    if runs.len() > 1 {
+        let diverged: Vec<usize> = (1..runs.len()).filter(|&i| runs[i] != runs[0]).collect();
+        if !diverged.is_empty() {
+            for (i, (mn, mx, sum, bad)) in runs.iter().enumerate() {
+                let bad = if *bad == usize::MAX { "none".to_string() } else { bad.to_string() };
+                eprintln!("  run {}: min_S={mn} max_S={mx} sum_S={sum} first mismatch={bad}", i + 1);
+            }
+            anyhow::bail!("verify --repeat: {} of {} runs disagree with run 1 (nondeterministic recompute)", diverged.len(), runs.len());
+        }
+        eprintln!("repeat: {} runs agree (min_S={} max_S={} sum_S={})", runs.len(), runs[0].0, runs[0].1, runs[0].2);
+    }
+
+    if !ok.load(std::sync::atomic::Ordering::Relaxed) {
+        // under --first-fail this is the lowest mismatch found before workers stopped
+        let idx = bad_idx.load(std::sync::atomic::Ordering::Relaxed);
+        let (s32, m) = recompute(idx);
+        let state = match m {
+            Some(m) => format!(" (end residue recomputed m={m} stored m={})", ends[idx]),
+            None => String::new(),
+        };
+        anyhow::bail!(
+            "value mismatch{}: index {idx} recomputed S={s32} stored S={}{state}",
+            if opts.first_fail { " (first-fail)" } else { "" },
+            table[idx]
+        );
+    }
+    // a spot check only recomputes part of the table; the global min then comes from the
+    // stored (hash-checked) values and is not independently established
+    let (min_s, max_s, sum_s) = if partial {
+        (
+            table.iter().copied().min().unwrap_or(u32::MAX),
+            table.iter().copied().max().unwrap_or(0),
+            table.iter().map(|&v| v as u64).sum::<u64>(),
+        )
+    } else {
+        (
+            recomputed_min.load(std::sync::atomic::Ordering::Relaxed),
+            recomputed_max.load(std::sync::atomic::Ordering::Relaxed),
+            recomputed_sum.load(std::sync::atomic::Ordering::Relaxed),
+        )
+    };
+    // check manifest (if any); its drift constant drives threshold and eps
+    let mf: Option<Manifest> = match &manifest_path {
+        Some(p) => Some(serde_json::from_reader(File::open(p)?)?),
+        None => None,
+    };
+    let c = match &mf {
+        Some(mf) => check_log_constant(mf.log_constant).map_err(|e| anyhow::anyhow!("manifest log_constant: {e}"))?,
+        None => log2_3(),
+    };
+    let thr = threshold_strict(l, c)?;
+    let pass = !header.partial() && min_s >= thr;
+    let eps = drift_eps(min_s, l, c);
+    let verified = VerifiedTable { k, l, ver, count, residues, min_s, max_s, sum_s, sha256_hex: hex(digest.as_slice()) };
+    if let Some(mf) = &mf {
+        check_manifest_fields(mf, &table, &opts, c, &verified)?;
+    }
+    if let Some(n) = opts.expect_min_s {
+        anyhow::ensure!(min_s == n, "--expect-min-s mismatch: expected={n} computed={min_s}");
+        eprintln!("expect-min-s: recomputed min_S={min_s} as expected");
+    }
+
+    opts.events.emit("complete", serde_json::json!({
+        "cmd": "verify", "min_s": min_s, "max_s": max_s, "threshold": thr, "pass": pass, "eps": eps, "partial": partial || header.partial(),
+    }))?;
+    if let Some(n) = opts.sample {
+        eprintln!("verify (sample): recomputed {n} random indices (seed={}) of {count} match; sha256 ok", opts.seed);
+        eprintln!("  stored min_S={min_s} thr={thr} eps={:.6}; full pass not established", eps);
+        return Ok(Verdict::NotCertified);
+    }
+    if partial {
+        eprintln!("verify (partial): recomputed indices [{lo},{hi}) of {count} match; sha256 ok");
+        eprintln!("  stored min_S={min_s} thr={thr} eps={:.6}; full pass not established", eps);
+        return Ok(Verdict::NotCertified);
+    }
+    if header.partial() {
+        eprintln!("verify (partial table): all {count} entries match, of {} residues; sha256 ok", residues.count(k));
+        eprintln!("  min_S={min_s} thr={thr} eps={eps:.6} over these entries only; full pass not established");
+        return Ok(Verdict::NotCertified);
+    }
+    if let Some(p) = &opts.metrics {
+        write_metrics(p, k, l, c, min_s, pass)?;
+    }
+    let margin = min_s as i64 - thr as i64;
+    anstream::eprintln!("verify: {}", fields(&[
+        kv("min_S", min_s), kv("thr", thr), kv_pass("pass", pass), kv("eps", format!("{eps:.6}")), kv("margin", margin),
+    ]));
+    if opts.explain {
+        explain_verdict(k, l, count as u64, residues, c, min_s)?;
+    }
+    check_min_margin(opts.min_margin, margin)?;
+    if let (true, Some(mf)) = (opts.summary_line, &mf) {
+        println!("{}", summary_line_of(mf, started.elapsed()));
+    }
+    if let Some(p) = &opts.rewrite_manifest {
+        let fresh = rewritten_manifest(mf.as_ref(), &verified, &table, c)?;
+        let mut f = File::create(p).map_err(path_io_error(p))?;
+        serde_json::to_writer_pretty(&mut f, &fresh)?;
+        f.flush()?;
+        eprintln!("rewrite-manifest: wrote {} (manifest_sha256={})", p.display(), fresh.manifest_sha256.as_deref().unwrap_or(""));
+    }
+    Ok(Verdict::Full { pass })
+}
+
+/// `verify --rewrite-manifest`: a manifest whose correctness fields all come from the
+/// recompute just done and whose optional fields are filled in for this schema. The
+/// provenance of the table (generator, build, timestamp, notes) is kept from the old
+/// manifest when there is one; without it, this process stands in as the generator.
+fn rewritten_manifest(old: Option<&Manifest>, t: &VerifiedTable, table: &[u32], c: f64) -> anyhow::Result<Manifest> {
+    let neg_bound = neg_drift_bound(t.l, c);
+    let thr = threshold_strict(t.l, c)?;
+    let mut mf = Manifest {
+        k: t.k,
+        l: t.l,
+        count: t.count as u64,
+        min_s: t.min_s,
+        max_s: Some(t.max_s),
+        mean_s: Some(t.sum_s as f64 / t.count as f64),
+        neg_drift_count: Some(table.iter().filter(|&&v| (v as u64) < neg_bound).count() as u64),
+        eps: drift_eps(t.min_s, t.l, c),
+        threshold: thr,
+        pass: t.min_s >= thr,
+        sha256_table_hex: t.sha256_hex.clone(),
+        sha256_exec_hex: std::env::current_exe().ok()
+            .and_then(|exe| sha256_file(&exe).ok())
+            .unwrap_or_else(|| "unknown".into()),
+        generator_cmdline: std::env::args().collect::<Vec<_>>().join(" "),
+        reproduce_cmd: reproduce_cmd(t.k, t.l, t.residues, t.ver == VER_STATE, None),
+        pkg_version: env!("CARGO_PKG_VERSION").to_string(),
+        build_git_rev: BUILD_GIT_REV.to_string(),
+        build_rustc: BUILD_RUSTC.to_string(),
+        os_arch: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+        gen_ts: chrono::Utc::now().to_rfc3339(),
+        file_ver: t.ver,
+        include_even: t.residues == Residues::All,
+        modulus_bits: Some(t.k),
+        residue_form: Some(t.residues.form().to_string()),
+        log_constant: c,
+        peak_rss_bytes: None,
+        notes: None,
+        manifest_sha256: None,
+    };
+    if let Some(old) = old {
+        mf.sha256_exec_hex = old.sha256_exec_hex.clone();
+        mf.generator_cmdline = old.generator_cmdline.clone();
+        if !old.reproduce_cmd.is_empty() {
+            mf.reproduce_cmd = old.reproduce_cmd.clone();
+        }
+        mf.pkg_version = old.pkg_version.clone();
+        mf.build_git_rev = old.build_git_rev.clone();
+        mf.build_rustc = old.build_rustc.clone();
+        mf.os_arch = old.os_arch.clone();
+        mf.gen_ts = old.gen_ts.clone();
+        mf.peak_rss_bytes = old.peak_rss_bytes;
+        mf.notes = old.notes.clone();
+    }
+    mf.manifest_sha256 = Some(manifest_sha256(&mf));
+    Ok(mf)
+}
+
+/// `verify`: the S values (and, for ver=3, end residues) of a table body. Runs on the
+/// current rayon pool; the fixed-width formats decode in parallel chunks.
+fn decode_body(bytes: &[u8], ver: u32, count: usize) -> anyhow::Result<(Vec<u32>, Vec<u32>)> {
+    let table = match ver {
+        VER_RLE => rle_decode(bytes, count as u64)?,
+        1 => bytes[..2 * count].par_chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]]) as u32).collect(),
+        VER_STATE => {
+            return Ok(bytes[..8 * count]
+                .par_chunks_exact(8)
+                .map(|e| (u32::from_le_bytes([e[0], e[1], e[2], e[3]]), u32::from_le_bytes([e[4], e[5], e[6], e[7]])))
+                .unzip());
+        }
+        _ => bytes[..4 * count].par_chunks_exact(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect(),
+    };
+    Ok((table, Vec::new()))
+}
+
+/// `verify --min-margin`: a pass by fewer than `want` units of S is a failure.
+fn check_min_margin(want: Option<i64>, margin: i64) -> anyhow::Result<()> {
+    let Some(want) = want else { return Ok(()) };
+    anyhow::ensure!(margin >= want, "--min-margin {want}: min_S - thr = {margin} is below the required margin");
+    eprintln!("min-margin: {margin} >= {want} ok");
+    Ok(())
+}
+
+/// `--prefetch`: say whether the read-ahead hint took effect (it never changes results).
+fn note_prefetch(path: &Path, applied: bool) {
+    if applied {
+        eprintln!("prefetch: sequential read-ahead hint applied to {}", path.display());
+    } else {
+        eprintln!("prefetch: not supported for {}; read it without the hint", path.display());
+    }
+}
+
+/// What the full or partial recompute established about a table, for the manifest checks.
+struct VerifiedTable {
+    k: u32,
+    l: u32,
+    ver: u32,
+    count: usize,
+    residues: Residues,
+    min_s: u32,
+    max_s: u32,
+    sum_s: u64,
+    sha256_hex: String,
+}
+
+/// `verify`: every manifest field that can be checked against the table.
+fn check_manifest_fields(mf: &Manifest, table: &[u32], opts: &VerifyOpts, c: f64, t: &VerifiedTable) -> anyhow::Result<()> {
+    let VerifiedTable { k, l, ver, count, residues, min_s, max_s, sum_s, ref sha256_hex } = *t;
+    check_manifest_self_hash(mf)?;
+    anyhow::ensure!(mf.k == k && mf.l == l && mf.count as usize == count, "manifest mismatch");
+    anyhow::ensure!(mf.sha256_table_hex == *sha256_hex, "manifest sha256 mismatch");
+    if mf.file_ver != 0 { anyhow::ensure!(mf.file_ver == ver, "manifest file_ver mismatch"); }
+    anyhow::ensure!(
+        mf.include_even == (residues == Residues::All),
+        "manifest include_even mismatch: manifest={} table={:?} residues", mf.include_even, residues
+    );
+    if let Some(form) = &mf.residue_form {
+        anyhow::ensure!(
+            form == residues.form(),
+            "manifest residue_form mismatch: manifest={form} table={}", residues.form()
+        );
+    }
+    // stored values equal the recomputed ones here (for a full run), so counting either is exact
+    let bound = neg_drift_bound(l, c);
+    let neg = table.iter().filter(|&&s| (s as u64) < bound).count() as u64;
+    check_recomputed_stats(mf, c, opts.eps_tolerance, RecomputedStats { min_s, max_s, sum_s, count: count as u64, neg_drift: neg })
+}
+
+/// A manifest's `manifest_sha256`, when it has one, must match its other fields.
+fn check_manifest_self_hash(mf: &Manifest) -> anyhow::Result<()> {
+    match &mf.manifest_sha256 {
+        Some(h) => anyhow::ensure!(
+            *h == manifest_sha256(mf),
+            "manifest_sha256 mismatch: manifest was modified after gen"
+        ),
+        None => eprintln!("note: manifest has no manifest_sha256; its fields are only cross-checked"),
+    }
+    Ok(())
+}
+
+/// Figures recomputed over every entry, to hold a manifest's statistics against.
+struct RecomputedStats {
+    min_s: u32,
+    max_s: u32,
+    sum_s: u64,
+    count: u64,
+    neg_drift: u64,
+}
+
+/// `verify` with or without the table: the manifest's min_s and optional statistics against
+/// the recompute, then its own consistency (threshold, pass, eps) on top of that min_s.
+fn check_recomputed_stats(mf: &Manifest, c: f64, eps_tol: f64, r: RecomputedStats) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        mf.min_s == r.min_s,
+        "manifest min_s mismatch: manifest={} computed={}", mf.min_s, r.min_s
+    );
+    // with min_s, k and l pinned to the recompute, the manifest's own consistency settles the rest
+    note_eps_tolerance(eps_tol);
+    if let Some((_, Err(e))) = manifest_consistency(mf, c, eps_tol).into_iter().find(|(_, r)| r.is_err()) {
+        anyhow::bail!(e);
+    }
+    if let Some(mx) = mf.max_s {
+        anyhow::ensure!(mx == r.max_s, "manifest max_s mismatch: manifest={mx} computed={}", r.max_s);
+    }
+    if let Some(mean) = mf.mean_s {
+        let mean2 = r.sum_s as f64 / r.count as f64;
+        anyhow::ensure!((mean - mean2).abs() < 1e-12, "manifest mean_s mismatch: manifest={mean} computed={mean2}");
+    }
+    if let Some(n) = mf.neg_drift_count {
+        anyhow::ensure!(n == r.neg_drift, "manifest neg_drift_count mismatch: manifest={n} computed={}", r.neg_drift);
+    }
+    Ok(())
+}
+
+/// `verify --explain`: the numbers behind the verdict, in words. Recomputes only the
+/// formulas from (l, c, min_s), exactly as `verify` does.
+fn explain_verdict(k: u32, l: u32, count: u64, residues: Residues, c: f64, min_s: u32) -> anyhow::Result<()> {
+    let thr = threshold_strict(l, c)?;
+    let eps = drift_eps(min_s, l, c);
+    let which = match residues {
+        Residues::Odd => format!("odd residue r mod 2^{k} ({count} in all)"),
+        Residues::All => format!("residue r mod 2^{k}, even ones included ({count} in all)"),
+    };
+    let constant = if c == log2_3() { "log2(3)".to_string() } else { format!("the --log-constant {c}") };
+    eprintln!("explain:");
+    eprintln!("  1. For each {which}, S_r is the number of halvings in the first L={l} odd steps");
+    eprintln!("     m -> (3m+1)/2^e of its trajectory, so those steps scale r by about 3^L / 2^S_r.");
+    eprintln!("  2. The trajectory shrinks on average when S_r/L exceeds c = {constant} = {c:.6}.");
+    eprintln!("  3. Threshold: thr = floor(L * c) + 1 = floor({l} * {c:.6}) + 1 = floor({:.6}) + 1 = {thr}.", l as f64 * c);
+    eprintln!("  4. Every entry was recomputed and matched the table; the smallest is min_S = {min_s}.");
+    if min_s >= thr {
+        eprintln!("  5. min_S = {min_s} >= thr = {thr}, so every residue reaches the threshold.");
+    } else {
+        eprintln!("  5. min_S = {min_s} < thr = {thr}, so at least one residue falls short of the threshold.");
+    }
+    if l == 0 {
+        eprintln!("  6. Margin: with L = 0 no steps are taken, so eps = -c = {eps:.6}.");
+    } else {
+        eprintln!("  6. Margin: eps = min_S/L - c = {min_s}/{l} - {c:.6} = {eps:.6}.");
+    }
+    if min_s >= thr {
+        eprintln!("  Verdict: PASS. Every residue's S/L is above c, by at least eps.");
+    } else {
+        eprintln!("  Verdict: FAIL. The certificate does not hold for this K and L.");
+    }
+    Ok(())
+}
+
+/// Default `--eps-tolerance`: far above f64 rounding of eps, far below any change in min_s.
+const EPS_TOLERANCE: f64 = 1e-12;
+
+fn parse_tolerance(s: &str) -> Result<f64, String> {
+    let t: f64 = s.trim().parse().map_err(|e| format!("bad tolerance: {e}"))?;
+    if !(t.is_finite() && t >= 0.0) {
+        return Err(format!("tolerance must be finite and >= 0, got {t}"));
+    }
+    Ok(t)
+}
+
+/// `--exact-eps` is a tolerance of zero.
+fn eps_tolerance_of(tolerance: f64, exact: bool) -> f64 {
+    if exact { 0.0 } else { tolerance }
+}
+
+fn note_eps_tolerance(tol: f64) {
+    if tol == 0.0 {
+        eprintln!("note: eps compared exactly (--exact-eps)");
+    } else if tol != EPS_TOLERANCE {
+        eprintln!("note: eps tolerance {tol:e} (default {EPS_TOLERANCE:e})");
+    }
+}
+
+/// Internal consistency of a manifest's threshold, pass and eps with its own l, min_s and drift
+/// constant `c`; one named result per check. eps may differ from the recomputed value by at
+/// most `eps_tol`.
+fn manifest_consistency(mf: &Manifest, c: f64, eps_tol: f64) -> Vec<(&'static str, Result<(), String>)> {
+    let thr = threshold_strict(mf.l, c);
+    // the manifest of a partial table (gen --max-index) never claims a pass
+    let residues = if mf.include_even { Residues::All } else { Residues::Odd };
+    let covered = !(1..=62).contains(&mf.k) || mf.count >= residues.count(mf.k);
+    let pass = covered && mf.min_s >= mf.threshold;
+    let eps = drift_eps(mf.min_s, mf.l, c);
+    vec![
+        ("threshold", match thr {
+            Ok(thr) if mf.threshold == thr => Ok(()),
+            Ok(thr) => Err(format!("manifest threshold mismatch: manifest={} expected={thr}", mf.threshold)),
+            Err(e) => Err(format!("manifest threshold: {e}")),
+        }),
+        ("pass", if mf.pass == pass { Ok(()) } else if !covered {
+            Err(format!("manifest pass=true but count={} covers only part of the {} residues", mf.count, residues.count(mf.k)))
+        } else {
+            Err(format!("manifest pass mismatch: manifest={} computed={pass}", mf.pass))
+        }),
+        ("eps", if (mf.eps - eps).abs() <= eps_tol { Ok(()) } else {
+            Err(format!("manifest eps mismatch: manifest={} computed={eps} (tolerance {eps_tol:e})", mf.eps))
+        }),
+        ("modulus_bits", match mf.modulus_bits {
+            Some(bits) if bits != mf.k => Err(format!("manifest modulus_bits mismatch: manifest={bits} k={}", mf.k)),
+            _ => Ok(()),
+        }),
+        ("residue_form", match mf.residue_form.as_deref().map(|f| (f, Residues::from_form(f))) {
+            Some((f, None)) => Err(format!("manifest residue_form unknown: {f:?} (expected \"odd\" or \"all\")")),
+            Some((f, Some(r))) if mf.include_even != (r == Residues::All) => Err(format!(
+                "manifest residue_form mismatch: residue_form={f} include_even={}", mf.include_even
+            )),
+            _ => Ok(()),
+        }),
+    ]
+}
+
+/// `validate-manifest`: self-consistency of a manifest alone, before the table is at hand.
+fn validate_manifest(path: &Path, eps_tol: f64) -> anyhow::Result<()> {
+    let mf: Manifest = serde_json::from_reader(File::open(path).map_err(path_io_error(path))?)?;
+    let c = check_log_constant(mf.log_constant)
+        .map_err(|e| anyhow::anyhow!("manifest log_constant: {e}"))?;
+    note_eps_tolerance(eps_tol);
+    let checks = manifest_consistency(&mf, c, eps_tol);
+    anstream::eprintln!("validate-manifest: {}", fields(&[kv("K", mf.k), kv("L", mf.l), kv("min_S", mf.min_s)]));
+    for (name, r) in &checks {
+        match r {
+            Ok(()) => eprintln!("  {name}: ok"),
+            Err(e) => eprintln!("  {name}: inconsistent ({e})"),
+        }
+    }
+    eprintln!("note: table not checked; sha256_table_hex and min_s are only confirmed by verify --table");
+    let bad = checks.iter().filter(|(_, r)| r.is_err()).count();
+    anyhow::ensure!(bad == 0, "validate-manifest: {bad} inconsistent field(s)");
+    Ok(())
+}
+
+/// Structural integrity only: the header/length/trailer checks of `read_table_bytes`,
+/// without recomputing any S values or reading a manifest.
+fn verify_structural(k: Option<u32>, l: Option<u32>, table_path: &Path) -> anyhow::Result<()> {
+    let (h, _table) = read_table_bytes(table_path)?;
+    let (k_file, l_file) = (h.k, h.l);
+    anyhow::ensure!(
+        k.unwrap_or(k_file) == k_file && l.unwrap_or(l_file) == l_file,
+        "K/L mismatch: file has k={k_file} l={l_file}"
+    );
+    eprintln!(
+        "verify (structural): magic=CALT ver={} K={k_file} L={l_file} count={} flags={:#x} algo_ver={}",
+        h.ver, h.count, h.flags, h.algo_ver
+    );
+    match h.body_len() {
+        Some(n) => eprintln!("  length={} bytes ok; sha256 trailer ok", 32 + n + 32),
+        None => eprintln!("  rle runs cover count ok; sha256 trailer ok"),
+    }
+    Ok(())
+}
+
+/// `equal`: identical tables have equal K/L/count and equal sha256 trailers. Each trailer is
+/// checked against its own body first, so a match means the bodies match.
+fn equal(a: &Path, b: &Path) -> anyhow::Result<()> {
+    let open = |p: &Path| TableReader::open(p).map_err(|e| anyhow::anyhow!("{}: {e}", p.display()));
+    let (ra, rb) = (open(a)?, open(b)?);
+    let (ha, hb) = (*ra.header(), *rb.header());
+    anyhow::ensure!(
+        (ha.k, ha.l, ha.count) == (hb.k, hb.l, hb.count),
+        "structurally different: {} has K={} L={} count={}, {} has K={} L={} count={}",
+        a.display(), ha.k, ha.l, ha.count, b.display(), hb.k, hb.l, hb.count
+    );
+    let trailer = |mut r: TableReader, p: &Path| -> anyhow::Result<[u8; 32]> {
+        for e in r.by_ref() {
+            e.map_err(|e| anyhow::anyhow!("{}: {e}", p.display()))?;
+        }
+        Ok(*r.sha256().expect("trailer checked at end of iteration"))
+    };
+    let (sa, sb) = (trailer(ra, a)?, trailer(rb, b)?);
+    if sa == sb {
+        eprintln!("equal: identical (K={} L={} count={} sha256={})", ha.k, ha.l, ha.count, hex(&sa));
+        return Ok(());
+    }
+    eprintln!("equal: differ (same K={} L={} count={})", ha.k, ha.l, ha.count);
+    eprintln!("  {}: ver={} sha256={}", a.display(), ha.ver, hex(&sa));
+    eprintln!("  {}: ver={} sha256={}", b.display(), hb.ver, hex(&sb));
+    if ha.ver != hb.ver {
+        eprintln!("  note: the encodings differ (ver {} vs {}), so the hashes cannot match even for equal values", ha.ver, hb.ver);
+    }
+    anyhow::bail!("tables differ")
+}
+
+/// `verify --no-table`: recompute the manifest's figures straight from K and L, in chunks as
+/// gen does, and check them with gen's threshold and eps formulas. The table hash is not
+/// checked — there is no table to check it against.
+fn verify_no_table(manifest_path: &Path, threads: usize, eps_tol: f64, min_margin: Option<i64>) -> anyhow::Result<()> {
+    let mf: Manifest = serde_json::from_reader(
+        File::open(manifest_path).map_err(path_io_error(manifest_path))?,
+    )?;
+    check_manifest_self_hash(&mf)?;
+    let (k, l) = (mf.k, mf.l);
+    anyhow::ensure!((2..=28).contains(&k), "k in [2,28]");
+    let c = check_log_constant(mf.log_constant).map_err(|e| anyhow::anyhow!("manifest log_constant: {e}"))?;
+    let residues = if mf.include_even { Residues::All } else { Residues::Odd };
+    let count = residues.count(k);
+    anyhow::ensure!(mf.count == count, "manifest count mismatch: manifest={} expected={count} for k={k}", mf.count);
+    let nthreads = if threads == 0 { std::thread::available_parallelism()?.get() } else { threads };
+    eprintln!("verify (no table): K={k} L={l} count={count} threads={nthreads}");
+
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(nthreads).build()?;
+    let walk = Walk { mask: (1u64 << k) - 1, l, residues, neg_bound: neg_drift_bound(l, c) };
+    let mut chunk = vec![0u32; min(GEN_CHUNK as u64, count) as usize];
+    let mut total = Summary::EMPTY;
+    let mut start = 0u64;
+    while start < count {
+        let n = min(GEN_CHUNK as u64, count - start) as usize;
+        let buf = &mut chunk[..n];
+        total = total.merge(pool.install(|| compute_chunk(buf, start, walk)));
+        start += n as u64;
+    }
+    let min_s = total.min;
+    check_recomputed_stats(&mf, c, eps_tol, RecomputedStats { min_s, max_s: total.max, sum_s: total.sum, count, neg_drift: total.neg })?;
+    let thr = threshold_strict(l, c)?;
+    let eps = drift_eps(min_s, l, c);
+    let margin = min_s as i64 - thr as i64;
+    anstream::eprintln!("verify (no table): {}", fields(&[
+        kv("min_S", min_s), kv("thr", thr), kv_pass("pass", min_s >= thr), kv("eps", format!("{eps:.6}")), kv("margin", margin),
+    ]));
+    eprintln!("  table sha256 not checked (--no-table): the certificate's table is not part of this check");
+    check_min_margin(min_margin, margin)
+}
+
+/// Manifest fields that describe the certificate itself; the rest record how and where
+/// it was produced.
+const CORRECTNESS_FIELDS: &[&str] = &[
+    "k", "l", "count", "min_s", "max_s", "mean_s", "neg_drift_count", "eps", "threshold", "pass", "sha256_table_hex",
+    "file_ver", "include_even", "modulus_bits", "residue_form", "log_constant",
+];
+
+/// Build provenance baked in by build.rs; "unknown" when git or rustc was unavailable.
+const BUILD_GIT_REV: &str = match option_env!("BUILD_GIT_REV") { Some(v) => v, None => "unknown" };
+const BUILD_RUSTC: &str = match option_env!("BUILD_RUSTC") { Some(v) => v, None => "unknown" };
+
+/// `verify --verify-build`: is this the build that generated the manifest? Only warns, since
+/// the certificate itself was already checked; a matching git rev and rustc identify the
+/// same source build even when the executable bytes differ.
+fn check_build(manifest_path: &Path) -> anyhow::Result<()> {
+    let mf: Manifest = serde_json::from_reader(File::open(manifest_path)?)?;
+    let known = |v: &str| !v.is_empty() && v != "unknown";
+    eprintln!("verify-build:");
+    let mut mismatch = 0;
+    for (name, recorded, ours) in [
+        ("build_git_rev", mf.build_git_rev.as_str(), BUILD_GIT_REV),
+        ("build_rustc", mf.build_rustc.as_str(), BUILD_RUSTC),
+    ] {
+        if !known(recorded) || !known(ours) {
+            eprintln!("  {name}: cannot compare (manifest={recorded:?} this binary={ours:?})");
+        } else if recorded == ours {
+            eprintln!("  {name}: same ({ours})");
+        } else {
+            mismatch += 1;
+            eprintln!("  {name}: differs (manifest={recorded} this binary={ours})");
+        }
+    }
+    let exe = std::env::current_exe().ok().and_then(|p| sha256_file(&p).ok());
+    match exe {
+        Some(h) if known(&mf.sha256_exec_hex) => {
+            eprintln!("  sha256_exec_hex: {}", if h == mf.sha256_exec_hex { "identical binary" } else { "different binary bytes" })
+        }
+        _ => eprintln!("  sha256_exec_hex: cannot compare"),
+    }
+    if mismatch > 0 {
+        eprintln!("warning: verifying binary is not the generating build ({mismatch} field(s) differ)");
+    }
+    Ok(())
+}
+
+/// Diff two manifests field by field, listing correctness and environmental differences
+/// separately; only the former are an error.
+fn compare_manifests(primary: &Path, other: &Path) -> anyhow::Result<()> {
+    let load = |p: &Path| -> anyhow::Result<serde_json::Map<String, serde_json::Value>> {
+        let mf: Manifest = serde_json::from_reader(File::open(p).map_err(path_io_error(p))?)?;
+        match serde_json::to_value(mf)? {
+            serde_json::Value::Object(m) => Ok(m),
+            _ => unreachable!("manifest serializes to an object"),
+        }
+    };
+    let (a, b) = (load(primary)?, load(other)?);
+    let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    let null = serde_json::Value::Null;
+    let (mut correctness, mut environment) = (Vec::new(), Vec::new());
+    for key in keys {
+        let (va, vb) = (a.get(key).unwrap_or(&null), b.get(key).unwrap_or(&null));
+        if va != vb {
+            let line = format!("    {key}: {va} != {vb}");
+            if CORRECTNESS_FIELDS.contains(&key.as_str()) { correctness.push(line) } else { environment.push(line) }
+        }
+    }
+    eprintln!("compare-manifest: {} vs {}", primary.display(), other.display());
+    for (title, lines) in [("correctness", &correctness), ("environment", &environment)] {
+        if lines.is_empty() {
+            eprintln!("  {title}: identical");
+        } else {
+            eprintln!("  {title}: {} field(s) differ", lines.len());
+            lines.iter().for_each(|line| eprintln!("{line}"));
+        }
+    }
+    anyhow::ensure!(correctness.is_empty(), "compare-manifest: {} correctness field(s) differ", correctness.len());
+    Ok(())
+}
+
+/// Entries per block when localising a sha256 mismatch.
+const DIAG_BLOCK: usize = 1 << 16;
+
+/// Hash the body in fixed-size blocks and compare each against the hash of the recomputed
+/// block; describes the first differing block as byte offsets (from file start) and indices.
+fn diagnose_mismatch(table_bytes: &[u8], width: usize, k: u32, l: u32, residues: Residues) -> String {
+    let mask: u64 = (1u64 << k) - 1;
+    let count = table_bytes.len() / width;
+    let nblocks = count.div_ceil(DIAG_BLOCK);
+    let first_bad = (0..nblocks).into_par_iter().find_first(|&b| {
+        let lo = b * DIAG_BLOCK;
+        let hi = min(lo + DIAG_BLOCK, count);
+        let mut expected = Sha256::new();
+        for idx in lo..hi {
+            match width {
+                2 => expected.update((residues.s_sum(mask, l, idx as u64) as u16).to_le_bytes()),
+                8 => {
+                    let (s, m) = residues.s_sum_state(mask, l, idx as u64);
+                    expected.update(s.to_le_bytes());
+                    expected.update((m as u32).to_le_bytes());
+                }
+                _ => expected.update(residues.s_sum(mask, l, idx as u64).to_le_bytes()),
+            }
+        }
+        let stored = Sha256::digest(&table_bytes[lo * width..hi * width]);
+        expected.finalize() != stored
+    });
+    match first_bad {
+        Some(b) => {
+            let lo = b * DIAG_BLOCK;
+            let hi = min(lo + DIAG_BLOCK, count);
+            format!(
+                "first differing block: bytes {}..{} (indices {}..{})",
+                32 + lo * width, 32 + hi * width, lo, hi
+            )
+        }
+        None => "body matches recomputation; trailer is corrupt".to_string(),
+    }
+}
+
+/// Optional stats inputs; the default reads and hash-checks every entry.
+struct StatsOpts {
+    /// Strided sample of at most this many entries
+    max_entries: Option<u64>,
+    no_hash: bool,
+    exact_histogram: bool,
+    /// Dense/sparse switch-over for the exact histogram
+    max_value_cap: u32,
+    out_json: Option<PathBuf>,
+    /// Earlier `--out-json` output to diff against, with the allowed |delta|
+    baseline: Option<PathBuf>,
+    tolerance: f64,
+    /// Cutoff for the upper-tail summary
+    above: Option<u32>,
+    /// Total width of the `--plot` chart, if one is drawn
+    plot_width: Option<usize>,
+    /// Sequential read-ahead hint on the table file
+    prefetch: bool,
+    /// Rank error of the streaming quantile sketch; the table is then not kept in memory
+    approx_quantiles: Option<f64>,
+}
+
+/// `stats --out-json` output; also what `--baseline` reads back.
+#[derive(Serialize, Deserialize)]
+struct StatsJson {
+    k: u32,
+    l: u32,
+    ver: u32,
+    count: u64,
+    /// Entries the figures cover (fewer than `count` under --max-entries)
+    sampled: u64,
+    min_s: u32,
+    max_s: u32,
+    mean_s: f64,
+    stddev_s: f64,
+    threshold: u32,
+    eps_min: f64,
+    neg_drift_count: u64,
+    log_constant: f64,
+}
+
+fn stats(table_path: PathBuf, bins: usize, out_csv: Option<PathBuf>, eps_hist: Option<usize>, c: f64, opts: StatsOpts) -> anyhow::Result<()> {
+    let mut reader = if opts.prefetch {
+        let (reader, applied) = TableReader::open_prefetch(&table_path)?;
+        note_prefetch(&table_path, applied);
+        reader
+    } else {
+        TableReader::open(&table_path)?
+    };
+    if opts.no_hash {
+        reader = reader.without_hash_check();
+    }
+    let h = *reader.header();
+    let (k, l, ver) = (h.k, h.l, h.ver);
+    let stride = match opts.max_entries {
+        Some(n) if n < h.count => h.count.div_ceil(n),
+        _ => 1,
+    };
+    // keep Err items so a bad trailer still fails the sample
+    let entries = reader
+        .filter(|e| e.as_ref().map_or(true, |&(i, _)| i % stride == 0))
+        .map(|e| e.map(|(_, s)| s));
+    let mut tally = Tally::new(neg_drift_bound(l, c), opts.above.unwrap_or(u32::MAX));
+    let mut sketch = opts.approx_quantiles.map(QuantileSketch::new);
+    let table = match sketch.as_mut() {
+        // --approx-quantiles: only the running figures and the sketch stay in memory
+        Some(sketch) => {
+            for v in entries {
+                let v = v?;
+                tally.add(v);
+                sketch.insert(v);
+            }
+            sketch.flush();
+            Vec::new()
+        }
+        None => {
+            let table = entries.collect::<anyhow::Result<Vec<u32>>>()?;
+            table.iter().for_each(|&v| tally.add(v));
+            table
+        }
+    };
+    let count = tally.count;
+    anyhow::ensure!(count > 0, "empty table");
+    let (mn, mx, mean) = (tally.min, tally.max, tally.sum / count as f64);
+    // two-pass over an in-memory table, Welford's running variance when streaming
+    let stddev = if sketch.is_none() {
+        (table.iter().map(|&v| (v as f64 - mean).powi(2)).sum::<f64>() / count as f64).sqrt()
+    } else {
+        (tally.m2 / count as f64).sqrt()
+    };
+    let (neg_drift, tail, tail_sum) = (tally.neg_drift, tally.tail, tally.tail_sum);
+    let thr = threshold_strict(l, c)?;
+    let eps = drift_eps(mn, l, c);
+    let eps_of = |v: u32| drift_eps(v, l, c);
+    // histogram, over S or (with --eps-hist) over eps_i; empty under --approx-quantiles
+    let (lo, width, hist) = match eps_hist {
+        Some(b) => {
+            let (lo, hi) = (eps_of(mn), eps_of(mx.max(mn + 1)));
+            let (width, hist) = histogram(table.iter().map(|&v| eps_of(v)), lo, hi, b);
+            (lo, width, hist)
+        }
+        None => s_histogram(&table, mn, mx, bins),
+    };
+    eprintln!("stats: K={k} L={l} ver={ver} count={}", h.count);
+    if stride > 1 {
+        eprintln!("  sample: {count} of {} entries (stride {stride}); figures below cover the sample only", h.count);
+    }
+    if opts.no_hash {
+        eprintln!("  warning: sha256 not checked (--no-hash); the values are unverified");
+    }
+    eprintln!("  {}", fields(&[kv("min_S", mn), kv("max_S", mx), kv("mean", format!("{mean:.3}")), kv("stddev", format!("{stddev:.3}"))]));
+    anstream::eprintln!("  {}", fields(&[kv("thr", thr), kv_pass("pass(min)", mn >= thr)]));
+    eprintln!("  {}", fields(&[kv("eps(min)", format!("{eps:.6}"))]));
+    if eps_hist.is_none() && sketch.is_none() && hist.len() < bins.max(1) {
+        eprintln!("  bins snapped to {} integer-width bins (requested {bins})", hist.len());
+    }
+    eprintln!("  {}", fields(&[kv("eps<0", neg_drift), kv("frac(eps<0)", format!("{:.6}", neg_drift as f64 / count as f64))]));
+    if h.partial() {
+        eprintln!("  partial table: entries [0, {}) of {} residues only", h.count, h.residues().count(k));
+    } else if stride == 1 && sketch.is_none() {
+        fixed_point_notes(&h, &table, mx);
+    }
+    if let Some(cut) = opts.above {
+        let tail_mean = if tail > 0 { format!("{:.3}", tail_sum as f64 / tail as f64) } else { "n/a".to_string() };
+        eprintln!("  {}", fields(&[
+            kv(&format!("S>={cut}"), tail),
+            kv("frac", format!("{:.6}", tail as f64 / count as f64)),
+            kv("mean(tail)", tail_mean),
+        ]));
+        if cut > mx {
+            eprintln!("  note: cutoff {cut} is above max_S={mx}; the tail is empty");
+        }
+    }
+    if let Some(sketch) = &sketch {
+        sketch_notes(sketch);
+    }
+    let summary = StatsJson {
+        k, l, ver, count: h.count, sampled: count as u64, min_s: mn, max_s: mx, mean_s: mean, stddev_s: stddev,
+        threshold: thr, eps_min: eps, neg_drift_count: neg_drift as u64, log_constant: c,
+    };
+    if let Some(p) = &opts.out_json {
+        let mut f = File::create(p).map_err(path_io_error(p))?;
+        serde_json::to_writer_pretty(&mut f, &summary)?;
+        writeln!(f)?;
+    }
+    if let Some(p) = &opts.baseline {
+        compare_stats_baseline(&summary, p, opts.tolerance)?;
+    }
+    if opts.exact_histogram {
+        let (dense, counts) = exact_histogram(&table, mx, opts.max_value_cap);
+        if dense {
+            eprintln!("  exact histogram: dense ({} slots), {} distinct values", mx as u64 + 1, counts.len());
+        } else {
+            eprintln!("  exact histogram: sparse (max_S > cap {}), {} distinct values", opts.max_value_cap, counts.len());
+        }
+        if let Some(csv) = out_csv {
+            let mut w = std::io::BufWriter::new(File::create(csv)?);
+            writeln!(w, "s,count")?;
+            for (v, n) in counts {
+                writeln!(w, "{v},{n}")?;
+            }
+        }
+        return Ok(());
+    }
+    if let Some(cols) = opts.plot_width {
+        // bins ending at or below the threshold hold failing entries
+        let cut = if eps_hist.is_some() { eps_of(thr) } else { thr as f64 };
+        plot_histogram(lo, width, &hist, eps_hist.is_some(), cut, cols);
+    }
+    if let Some(csv) = out_csv {
+        let mut w = std::io::BufWriter::new(File::create(csv)?);
+        writeln!(w, "bin_lo,bin_hi,count")?;
+        for (i, c) in hist.iter().enumerate() {
+            let b_lo = lo + (i as f64)*width;
+            let b_hi = lo + ((i+1) as f64)*width;
+            writeln!(w, "{:.6},{:.6},{}", b_lo, b_hi, c)?;
+        }
+    }
+    Ok(())
+}
+
+/// `stats --plot` width: `--width`, else `$COLUMNS` when stderr is a terminal, else 80.
+fn plot_width(width: Option<u16>) -> usize {
+    use std::io::IsTerminal;
+    if let Some(w) = width {
+        return w as usize;
+    }
+    let columns = std::env::var("COLUMNS").ok().and_then(|v| v.trim().parse::<usize>().ok());
+    match columns {
+        Some(w) if w >= 20 && std::io::stderr().is_terminal() => w,
+        _ => 80,
+    }
+}
+
+/// `stats --plot`: one line per bin, `[lo, hi) count ###`, bars scaled so the fullest bin
+/// fills the row. Bars of failing bins (hi <= `cut`) are red, the rest green; `anstream`
+/// strips the colour for --no-color, NO_COLOR and non-TTY stderr.
+fn plot_histogram(lo: f64, width: f64, hist: &[usize], eps: bool, cut: f64, cols: usize) {
+    let edge = |i: usize| {
+        let x = lo + (i as f64)*width;
+        if eps { format!("{x:.6}") } else if width.fract() == 0.0 { format!("{x:.0}") } else { format!("{x:.1}") }
+    };
+    let labels: Vec<String> = (0..hist.len()).map(|i| format!("[{}, {})", edge(i), edge(i + 1))).collect();
+    let label_w = labels.iter().map(|s| s.len()).max().unwrap_or(0);
+    let peak = hist.iter().copied().max().unwrap_or(0);
+    let count_w = peak.to_string().len();
+    let bar_w = cols.saturating_sub(label_w + count_w + 2).max(1);
+    for (i, (label, &n)) in labels.iter().zip(hist).enumerate() {
+        // any non-empty bin gets at least one mark
+        let len = if peak == 0 || n == 0 { 0 } else { (n * bar_w).div_ceil(peak) };
+        let color = if lo + ((i + 1) as f64)*width <= cut { anstyle::AnsiColor::Red } else { anstyle::AnsiColor::Green };
+        let style = anstyle::Style::new().fg_color(Some(color.into()));
+        if len == 0 {
+            eprintln!("{label:<label_w$} {n:>count_w$}");
+            continue;
+        }
+        anstream::eprintln!("{label:<label_w$} {n:>count_w$} {style}{}{style:#}", "#".repeat(len));
+    }
+}
+
+/// `stats`: the fixed points of the step map, whose entries are exactly `e*L`, and the
+/// all-ones residue for contrast, which looks fixed 2-adically but is not mod 2^K.
+fn fixed_point_notes(h: &Header, table: &[u32], mx: u32) {
+    let index_of = |r: u64| match h.residues() { Residues::Odd => r >> 1, Residues::All => r };
+    for (r, e) in fixed_points(h.k) {
+        let s = table[index_of(r) as usize];
+        let at_max = if s == mx { " = max_S" } else { "" };
+        eprintln!("  fixed point: residue={r} (index {}) e={e} every step, S={e}*L={s}{at_max}", index_of(r));
+    }
+    let ones = (1u64 << h.k) - 1;
+    eprintln!(
+        "  all-ones residue {ones} (index {}): S={}; not a fixed point mod 2^K (each step drops its top bit)",
+        index_of(ones), table[index_of(ones) as usize]
+    );
+}
+
+/// `stats --baseline`: print the change in each distribution figure since `prev` and fail
+/// if any moved by more than `tol`.
+fn compare_stats_baseline(cur: &StatsJson, prev: &Path, tol: f64) -> anyhow::Result<()> {
+    let base: StatsJson = serde_json::from_reader(File::open(prev).map_err(path_io_error(prev))?)
+        .map_err(|e| anyhow::anyhow!("baseline {}: {e}", prev.display()))?;
+    anyhow::ensure!(
+        (base.k, base.l, base.sampled) == (cur.k, cur.l, cur.sampled),
+        "baseline {} covers K={} L={} ({} entries), not K={} L={} ({} entries)",
+        prev.display(), base.k, base.l, base.sampled, cur.k, cur.l, cur.sampled
+    );
+    eprintln!("baseline: {} (tolerance {tol:e})", prev.display());
+    let mut moved = 0;
+    for (name, was, now) in [
+        ("min_S", base.min_s as f64, cur.min_s as f64),
+        ("max_S", base.max_s as f64, cur.max_s as f64),
+        ("mean", base.mean_s, cur.mean_s),
+        ("stddev", base.stddev_s, cur.stddev_s),
+    ] {
+        let delta = now - was;
+        let flag = if delta.abs() > tol { moved += 1; "  CHANGED" } else { "" };
+        eprintln!("  {name}: {was} -> {now} (delta {delta:+e}){flag}");
+    }
+    anyhow::ensure!(moved == 0, "baseline: {moved} figure(s) moved beyond tolerance {tol:e}");
+    Ok(())
+}
+
+/// Equal-width histogram over [lo, hi] with `bins` bins (at least one); values outside
+/// the range clamp into the edge bins. Returns the bin width and counts.
+fn histogram(values: impl Iterator<Item = f64>, lo: f64, hi: f64, bins: usize) -> (f64, Vec<usize>) {
+    let bins = bins.max(1);
+    let width = (hi - lo) / (bins as f64);
+    let mut hist = vec![0usize; bins];
+    for v in values {
+        // a degenerate (zero/NaN) width puts everything in the first bin
+        let pos = if width > 0.0 { ((v - lo) / width).floor() } else { 0.0 };
+        let idx = if pos.is_nan() || pos < 0.0 { 0 } else { (pos as usize).min(bins - 1) };
+        hist[idx] += 1;
+    }
+    (width, hist)
+}
+
+/// Histogram of integer S values in [mn, mx]. When the requested bins would be narrower
+/// than one S unit, snap to one bin per integer value [v, v+1) so bin edges fall on
+/// integers and indices are computed exactly. Returns (lo, width, counts).
+fn s_histogram(table: &[u32], mn: u32, mx: u32, bins: usize) -> (f64, f64, Vec<usize>) {
+    let span = mx.checked_sub(mn).expect("min <= max") as usize;
+    let bins = bins.max(1);
+    if bins > span {
+        let mut hist = vec![0usize; span + 1];
+        for &v in table {
+            let idx = v.checked_sub(mn).map_or(0, |d| min(d as usize, span));
+            hist[idx] += 1;
+        }
+        return (mn as f64, 1.0, hist);
+    }
+    let (width, hist) = histogram(table.iter().map(|&v| v as f64), mn as f64, mx as f64, bins);
+    (mn as f64, width, hist)
+}
+
+/// Running figures of `stats`, one value at a time: min/max, sum and Welford's M2 for the
+/// mean and variance, the drift-sign count and the `--above` tail.
+struct Tally {
+    neg_bound: u64,
+    above: u32,
+    count: usize,
+    min: u32,
+    max: u32,
+    sum: f64,
+    mean: f64,
+    m2: f64,
+    neg_drift: usize,
+    tail: usize,
+    tail_sum: u64,
+}
+
+impl Tally {
+    fn new(neg_bound: u64, above: u32) -> Self {
+        Tally { neg_bound, above, count: 0, min: u32::MAX, max: 0, sum: 0.0, mean: 0.0, m2: 0.0, neg_drift: 0, tail: 0, tail_sum: 0 }
+    }
+
+    fn add(&mut self, v: u32) {
+        self.count += 1;
+        self.min = min(self.min, v);
+        self.max = max(self.max, v);
+        self.sum += v as f64;
+        let delta = v as f64 - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (v as f64 - self.mean);
+        if (v as u64) < self.neg_bound {
+            self.neg_drift += 1;
+        }
+        if v >= self.above {
+            self.tail += 1;
+            self.tail_sum += v as u64;
+        }
+    }
+}
+
+/// Greenwald-Khanna quantile summary: any quantile it returns has a rank within
+/// `err * count` of the requested one, in O((1/err) log(err * count)) tuples. Values are
+/// buffered and merged in sorted batches, as in Spark's `QuantileSummaries`.
+struct QuantileSketch {
+    err: f64,
+    count: u64,
+    /// (value, g, delta): g = rmin(i) - rmin(i-1), delta = rmax(i) - rmin(i); sorted by value
+    tuples: Vec<(u32, u64, u64)>,
+    buffer: Vec<u32>,
+    /// Largest `tuples.len()` seen, for the memory report
+    peak: usize,
+}
+
+impl QuantileSketch {
+    fn new(err: f64) -> Self {
+        QuantileSketch { err, count: 0, tuples: Vec::new(), buffer: Vec::new(), peak: 0 }
+    }
+
+    /// Values buffered between merges: one compression period, 1/(2 err)
+    fn batch(&self) -> usize {
+        ((0.5 / self.err).ceil() as usize).max(1)
+    }
+
+    fn insert(&mut self, v: u32) {
+        self.buffer.push(v);
+        if self.buffer.len() >= self.batch() {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut batch = std::mem::take(&mut self.buffer);
+        batch.sort_unstable();
+        let old = std::mem::take(&mut self.tuples);
+        let mut merged = Vec::with_capacity(old.len() + batch.len());
+        let mut next = 0;
+        for (j, &v) in batch.iter().enumerate() {
+            while next < old.len() && old[next].0 <= v {
+                merged.push(old[next]);
+                next += 1;
+            }
+            self.count += 1;
+            // a new minimum or maximum has an exact rank
+            let delta = if merged.is_empty() || (next == old.len() && j == batch.len() - 1) {
+                0
+            } else {
+                (2.0 * self.err * self.count as f64).floor() as u64
+            };
+            merged.push((v, 1, delta));
+        }
+        merged.extend_from_slice(&old[next..]);
+        self.peak = max(self.peak, merged.len());
+        self.tuples = self.compress(merged);
+    }
+
+    /// Merge neighbours whose combined rank uncertainty stays under 2 err count, keeping
+    /// the first and last tuple (the exact min and max).
+    fn compress(&self, tuples: Vec<(u32, u64, u64)>) -> Vec<(u32, u64, u64)> {
+        if tuples.len() <= 2 {
+            return tuples;
+        }
+        let limit = (2.0 * self.err * self.count as f64).floor() as u64;
+        let mut out = Vec::with_capacity(tuples.len());
+        let mut head = tuples[tuples.len() - 1];
+        for &t in tuples[1..tuples.len() - 1].iter().rev() {
+            if t.1 + head.1 + head.2 < limit {
+                head.1 += t.1;
+            } else {
+                out.push(head);
+                head = t;
+            }
+        }
+        out.push(head);
+        out.push(tuples[0]);
+        out.reverse();
+        out
+    }
+
+    /// The value at quantile `q` in [0, 1]; call after `flush`.
+    fn quantile(&self, q: f64) -> u32 {
+        let rank = (q * self.count as f64).ceil().max(1.0) as u64;
+        let slack = (self.err * self.count as f64).floor() as u64;
+        let mut rmin = 0u64;
+        for &(v, g, delta) in &self.tuples {
+            rmin += g;
+            if rmin + delta <= rank + slack && rank <= rmin + slack {
+                return v;
+            }
+        }
+        self.tuples.last().map_or(0, |t| t.0)
+    }
+}
+
+/// `stats --approx-quantiles`: the sketched percentiles with their rank error bound.
+fn sketch_notes(sketch: &QuantileSketch) {
+    let slack = (sketch.err * sketch.count as f64).floor() as u64;
+    eprintln!(
+        "  quantiles (GK sketch, err={}): rank within +/-{slack} of {} entries; {} tuples kept (peak {})",
+        sketch.err, sketch.count, sketch.tuples.len(), sketch.peak,
+    );
+    let points = [("p1", 0.01), ("p5", 0.05), ("p25", 0.25), ("p50", 0.5), ("p75", 0.75), ("p95", 0.95), ("p99", 0.99)];
+    let values: Vec<String> = points.iter().map(|&(name, q)| format!("{name}={}", sketch.quantile(q))).collect();
+    eprintln!("  {}", values.join(" "));
+}
+
+/// Exact count per distinct S value, ascending. A dense array indexed by value is used while
+/// `mx <= cap`; past that a sparse map keeps memory proportional to the distinct values.
+/// Returns whether the dense path was taken.
+fn exact_histogram(table: &[u32], mx: u32, cap: u32) -> (bool, Vec<(u32, u64)>) {
+    if mx <= cap {
+        let mut hist = vec![0u64; mx as usize + 1];
+        for &v in table {
+            hist[v as usize] += 1;
+        }
+        let counts = hist.into_iter().enumerate().filter(|&(_, n)| n > 0).map(|(v, n)| (v as u32, n)).collect();
+        return (true, counts);
+    }
+    let mut hist = std::collections::HashMap::<u32, u64>::new();
+    for &v in table {
+        *hist.entry(v).or_default() += 1;
+    }
+    let mut counts: Vec<(u32, u64)> = hist.into_iter().collect();
+    counts.sort_unstable();
+    (false, counts)
+}
+
+fn threshold(l: u32, min_s: Option<u32>, c: f64) -> anyhow::Result<()> {
+    let thr = threshold_strict(l, c)?;
+    println!("{}", fields(&[kv("L", l), kv("c", format!("{c:.6}")), kv("L*c", format!("{:.6}", l as f64 * c)), kv("thr", thr)]));
+    if let Some(m) = min_s {
+        let eps = drift_eps(m, l, c);
+        anstream::println!("{}", fields(&[kv("min_S", m), kv("eps", format!("{eps:.6}")), kv_pass("pass", m >= thr)]));
+    }
+    Ok(())
+}
+
+/// min_S at every L in 1..=l_max: each residue's trajectory is walked once to l_max and
+/// its running S folded into the per-L minimum, which costs the same as one gen at l_max.
+fn min_s_profile(k: u32, l_max: u32, residues: Residues) -> Vec<u32> {
+    let mask: u64 = (1u64 << k) - 1;
+    let fresh = || vec![u32::MAX; l_max as usize];
+    (0..residues.count(k)).into_par_iter().fold(fresh, |mut mins, idx| {
+        // zero steps: the start residue, after the leading halvings of an even one
+        let (mut s, mut m) = residues.s_sum_state(mask, 0, idx);
+        for slot in mins.iter_mut() {
+            let (e, next) = steps(mask, 1, m);
+            s = s.saturating_add(e);
+            m = next;
+            *slot = min(*slot, s);
+        }
+        mins
+    }).reduce(fresh, |a, b| a.into_iter().zip(b).map(|(x, y)| min(x, y)).collect())
+}
+
+/// How often each exponent e (halvings of 3m+1) occurs over the first `l` steps of every
+/// odd residue mod 2^k; index e of the result. e >= 1 always, and e <= k+2 since m < 2^k.
+fn exponent_histogram(k: u32, l: u32) -> Vec<u64> {
+    let mask: u64 = (1u64 << k) - 1;
+    let fresh = || vec![0u64; k as usize + 3];
+    (0..Residues::Odd.count(k)).into_par_iter().fold(fresh, |mut hist, idx| {
+        let mut m = (idx << 1) | 1;
+        for _ in 0..l {
+            let (e, next) = steps(mask, 1, m);
+            hist[e as usize] += 1;
+            m = next;
+        }
+        hist
+    }).reduce(fresh, |a, b| a.into_iter().zip(b).map(|(x, y)| x + y).collect())
+}
+
+/// `bit-analysis`: where S comes from. S/L is the mean exponent, so the distribution of e
+/// (about 2^-e for random odd m, mean 2) shows how far it sits above log2(3).
+fn bit_analysis(k: u32, l: u32, threads: usize, out: Option<&Path>) -> anyhow::Result<()> {
+    anyhow::ensure!((2..=28).contains(&k), "k in [2,28]");
+    anyhow::ensure!(l >= 1, "l >= 1");
+    let nthreads = if threads == 0 { std::thread::available_parallelism()?.get() } else { threads };
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(nthreads).build()?;
+    let hist = pool.install(|| exponent_histogram(k, l));
+    let steps_total: u64 = hist.iter().sum();
+    let e_total: u64 = hist.iter().enumerate().map(|(e, &n)| e as u64 * n).sum();
+    let mut w: Box<dyn Write> = match out {
+        Some(p) => Box::new(std::io::BufWriter::new(File::create(p).map_err(path_io_error(p))?)),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    writeln!(w, "exponent,count")?;
+    let last = hist.iter().rposition(|&n| n > 0).unwrap_or(0);
+    for (e, n) in hist.iter().enumerate().take(last + 1).skip(1) {
+        writeln!(w, "{e},{n}")?;
+    }
+    w.flush()?;
+    let mean = e_total as f64 / steps_total as f64;
+    eprintln!("bit-analysis: K={k} L={l} steps={steps_total}");
+    eprintln!("  {}", fields(&[kv("mean_e", format!("{mean:.6}")), kv("c", format!("{:.6}", log2_3())), kv("margin", format!("{:.6}", mean - log2_3()))]));
+    Ok(())
+}
+
+/// `find-boundary`: where `pass` (min_S >= floor(L*c)+1) changes as L grows. pass need not
+/// be monotone in L, so rather than binary-searching on that assumption every L up to l_max
+/// is computed and every flip is listed; the first one is the boundary.
+fn find_boundary(k: u32, l_max: u32, residues: Residues, c: f64, threads: usize, out_csv: Option<&Path>) -> anyhow::Result<()> {
+    anyhow::ensure!((2..=28).contains(&k), "k in [2,28]");
+    let nthreads = if threads == 0 { std::thread::available_parallelism()?.get() } else { threads };
+    eprintln!("find-boundary: K={k} L=1..={l_max} count={} threads={nthreads}", residues.count(k));
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(nthreads).build()?;
+    let thrs = (1..=l_max).map(|l| threshold_strict(l, c)).collect::<anyhow::Result<Vec<u32>>>()?;
+    let mins = pool.install(|| min_s_profile(k, l_max, residues));
+    let row = |l: u32| {
+        let (m, thr) = (mins[l as usize - 1], thrs[l as usize - 1]);
+        (m, thr, m >= thr)
+    };
+    if let Some(p) = out_csv {
+        let mut w = std::io::BufWriter::new(File::create(p).map_err(path_io_error(p))?);
+        writeln!(w, "l,min_s,threshold,pass")?;
+        for l in 1..=l_max {
+            let (m, thr, pass) = row(l);
+            writeln!(w, "{l},{m},{thr},{pass}")?;
+        }
+        w.flush()?;
+    }
+    let (m, thr, first) = row(1);
+    anstream::println!("{}", fields(&[kv("L", 1), kv("min_S", m), kv("thr", thr), kv_pass("pass", first)]));
+    let flips: Vec<u32> = (2..=l_max).filter(|&l| row(l).2 != row(l - 1).2).collect();
+    for &l in &flips {
+        let (m, thr, pass) = row(l);
+        anstream::println!("{}", fields(&[kv("L", l), kv("min_S", m), kv("thr", thr), kv_pass("pass", pass)]));
+    }
+    match flips.first() {
+        None => eprintln!("no flip up to L={l_max}: pass={first} throughout"),
+        Some(&l) => {
+            eprintln!("boundary: pass flips {first} -> {} at L={l}", !first);
+            if flips.len() > 1 {
+                eprintln!("  note: pass is not monotone in L here ({} flips up to L={l_max}); all are listed", flips.len());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rows between flushes in `export`, so a consumer of a pipe sees steady progress.
+const EXPORT_FLUSH_ROWS: u64 = 1 << 20;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, clap::ValueEnum)]
+enum ExportFormat {
+    Csv,
+    /// The S values as a flat u32 array: a data conduit with no metadata or integrity check
+    RawLe,
+}
+
+/// `export`: entries streamed from `TableReader` straight to the output, never holding the
+/// table. The sha256 trailer is only known after the last row, so a mismatch removes a partly
+/// written --out file (rows already sent to stdout cannot be taken back).
+fn export(table_path: &Path, out: Option<&Path>, format: ExportFormat, no_header: bool) -> anyhow::Result<()> {
+    let reader = TableReader::open(table_path)?;
+    let h = *reader.header();
+    let sink: Box<dyn Write> = match out {
+        Some(p) => Box::new(File::create(p).map_err(path_io_error(p))?),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    let mut w = std::io::BufWriter::new(sink);
+    let write_rows = || -> anyhow::Result<()> {
+        if format == ExportFormat::Csv && !no_header {
+            writeln!(w, "index,s")?;
+        }
+        for entry in reader {
+            let (i, s) = entry?;
+            match format {
+                ExportFormat::Csv => writeln!(w, "{i},{s}")?,
+                ExportFormat::RawLe => w.write_all(&s.to_le_bytes())?,
+            }
+            if (i + 1).is_multiple_of(EXPORT_FLUSH_ROWS) {
+                w.flush()?;
+            }
+        }
+        w.flush()?;
+        Ok(())
+    };
+    if let Err(e) = write_rows() {
+        if let Some(p) = out {
+            let _ = std::fs::remove_file(p);
+        }
+        return Err(e);
+    }
+    eprintln!("export: K={} L={} ver={} rows={}", h.k, h.l, h.ver, h.count);
+    if format == ExportFormat::RawLe {
+        eprintln!("  raw-le: {} bytes of u32 values; no header, hash or parameters travel with them", h.count * 4);
+    }
+    Ok(())
+}
+
+/// `import`: read index,s rows into their slots, stopping at the first problem (bad row,
+/// index out of range, duplicate, value outside u32, then the first missing index). Without
+/// `out` nothing is written. The values are taken as given; `verify` recomputes them.
+fn import(csv: &Path, k: u32, l: u32, residues: Residues, out: Option<&Path>) -> anyhow::Result<()> {
+    use std::io::BufRead;
+    anyhow::ensure!((2..=28).contains(&k), "k in [2,28]");
+    let count = residues.count(k);
+    let input: Box<dyn Read> = if is_stdin(csv) {
+        Box::new(std::io::stdin().lock())
+    } else {
+        Box::new(File::open(csv).map_err(path_io_error(csv))?)
+    };
+    // value per index, with the 1-based line that set it (0 = not seen yet)
+    let mut values = vec![0u32; count as usize];
+    let mut seen_at = vec![0usize; count as usize];
+    let mut rows = 0u64;
+    for (n, line) in BufReader::new(input).lines().enumerate() {
+        let (lineno, line) = (n + 1, line.map_err(path_io_error(csv))?);
+        let line = line.trim();
+        if line.is_empty() || (lineno == 1 && line == "index,s") {
+            continue;
+        }
+        let at = |msg: String| anyhow::anyhow!("{}:{lineno}: {msg}", csv.display());
+        let (i, v) = line.split_once(',').ok_or_else(|| at(format!("expected index,s, got {line:?}")))?;
+        let i: u64 = i.trim().parse().map_err(|e| at(format!("bad index {i:?}: {e}")))?;
+        anyhow::ensure!(i < count, at(format!("index {i} out of range: k={k} has {count} entries")));
+        let v: i128 = v.trim().parse().map_err(|e| at(format!("bad value {v:?}: {e}")))?;
+        let v = u32::try_from(v).map_err(|_| at(format!("value {v} at index {i} is not a u32")))?;
+        let slot = i as usize;
+        anyhow::ensure!(seen_at[slot] == 0, at(format!("duplicate index {i} (first on line {})", seen_at[slot])));
+        (values[slot], seen_at[slot]) = (v, lineno);
+        rows += 1;
+    }
+    if let Some(missing) = seen_at.iter().position(|&at| at == 0) {
+        anyhow::bail!("{}: index {missing} is missing ({rows} of {count} entries present)", csv.display());
+    }
+    let min_s = values.iter().copied().min().unwrap_or(0);
+    let Some(out) = out else {
+        eprintln!("import (validate only): {rows} rows cover [0,{count}) exactly once; values fit u32; min_S={min_s}");
+        return Ok(());
+    };
+    let digest = TableBuilder::new(k, l).residues(residues).write_file(out, values)?;
+    eprintln!("import: wrote {} (K={k} L={l} count={count} min_S={min_s} sha256={})", out.display(), hex(&digest));
+    eprintln!("  note: the values are imported as given; verify --expect-min-s or a manifest recomputes them");
+    Ok(())
+}
+
+/// `compact`: re-encode a table's values as ver=4 runs. The values and the table checks are
+/// unchanged, so a rewritten manifest only differs in file_ver and the table hash.
+fn compact(table_path: &Path, out: &Path, manifest: Option<(&Path, &Path)>) -> anyhow::Result<()> {
+    let (h, table) = read_table_bytes(table_path)?;
+    anyhow::ensure!(
+        h.ver != VER_STATE && h.ver != VER_RLE,
+        "compact: {} is ver={}; only ver=1/2 tables can be compacted", table_path.display(), h.ver
+    );
+    let body = rle_encode(&table);
+    let digest = Sha256::digest(&body);
+    let mut f = std::io::BufWriter::new(File::create(out).map_err(path_io_error(out))?);
+    write_header(&mut f, &Header { ver: VER_RLE, ..h })?;
+    f.write_all(&body)?;
+    f.write_all(&digest)?;
+    f.flush()?;
+    let before = h.body_len().expect("ver=1/2 tables have a fixed length");
+    eprintln!(
+        "compact: {} entries -> {} runs; body {before} -> {} bytes ({:.1}%)",
+        h.count, body.len() / 8, body.len(), 100.0 * body.len() as f64 / before as f64
+    );
+    if body.len() > before {
+        eprintln!("  note: runs are too short here for RLE to pay off; the ver={} table is smaller", h.ver);
+    }
+    if let Some((src, dst)) = manifest {
+        let mut mf: Manifest = serde_json::from_reader(File::open(src).map_err(path_io_error(src))?)?;
+        anyhow::ensure!(
+            mf.sha256_table_hex == hex(&Sha256::digest(table_bytes_of(&h, &table))),
+            "compact: manifest {} does not describe {}", src.display(), table_path.display()
+        );
+        mf.file_ver = VER_RLE;
+        mf.sha256_table_hex = hex(&digest);
+        mf.manifest_sha256 = Some(manifest_sha256(&mf));
+        let mut f = File::create(dst).map_err(path_io_error(dst))?;
+        serde_json::to_writer_pretty(&mut f, &mf)?;
+        f.flush()?;
+    }
+    eprintln!("table.sha256={}", hex(&digest));
+    Ok(())
+}
+
+/// Re-encode decoded values in their original fixed-width layout (ver=1 or 2).
+fn table_bytes_of(h: &Header, table: &[u32]) -> Vec<u8> {
+    match h.ver {
+        1 => table.iter().flat_map(|&v| (v as u16).to_le_bytes()).collect(),
+        _ => table.iter().flat_map(|&v| v.to_le_bytes()).collect(),
+    }
+}
+
+/// Optional pack inputs; the default bundles table and manifest at the archive root.
+struct PackOpts {
+    checksums: bool,
+    tar_root: Option<PathBuf>,
+    dry_run: bool,
+    no_hash: bool,
+    /// Leave the table out of the archive
+    manifest_only: bool,
+}
+
+fn pack(table_path: Option<PathBuf>, manifest_path: PathBuf, out: Option<PathBuf>, opts: PackOpts) -> anyhow::Result<()> {
+    let PackOpts { checksums, tar_root, dry_run, no_hash, manifest_only } = opts;
+    if let Some(root) = &tar_root {
+        anyhow::ensure!(
+            root.components().next().is_some()
+                && root.components().all(|c| matches!(c, std::path::Component::Normal(_))),
+            "--tar-root must be a plain relative directory, got {}", root.display()
+        );
+    }
+    // verify and extract header fields; a manifest-only bundle is named from the manifest
+    let (k, l, ver) = if manifest_only {
+        let mf = check_manifest_only(table_path.as_deref(), &manifest_path)?;
+        (mf.k, mf.l, mf.file_ver)
+    } else {
+        let table_path = table_path.as_ref().expect("clap requires --table without --manifest-only");
+        anyhow::ensure!(!is_stdin(table_path), "pack: --table must be a file, not stdin");
+        let h = if no_hash { *TableReader::open(table_path)?.header() } else { read_table_bytes(table_path)?.0 };
+        (h.k, h.l, h.ver)
+    };
+    // default out name
+    let out_path = out.unwrap_or_else(|| match manifest_only {
+        true => PathBuf::from(format!("cert_k{}_l{}_v{}_manifest.tar.gz", k, l, ver)),
+        false => PathBuf::from(format!("cert_k{}_l{}_v{}.tar.gz", k, l, ver)),
+    });
+    // add files with just their basenames, under --tar-root if given
+    let in_root = |name: &std::ffi::OsStr| tar_root.as_ref().map_or_else(|| PathBuf::from(name), |r| r.join(name));
+    let manifest_name = in_root(manifest_path.file_name().unwrap_or_else(|| std::ffi::OsStr::new("manifest.json")));
+    let mut entries = Vec::new();
+    if let (false, Some(t)) = (manifest_only, &table_path) {
+        entries.push((t.clone(), in_root(t.file_name().unwrap_or_else(|| std::ffi::OsStr::new("table.bin")))));
+    }
+    entries.push((manifest_path.clone(), manifest_name));
+    if dry_run {
+        println!("pack (dry run): would write {}", out_path.display());
+        for (path, name) in &entries {
+            let len = std::fs::metadata(path).map_err(path_io_error(path))?.len();
+            println!("  {}  {len} bytes", name.display());
+        }
+        if no_hash && !manifest_only {
+            println!("  table sha256 not checked (--no-hash)");
+        }
+        if checksums {
+            println!("  and CHECKSUMS.sha256");
+        }
+        return Ok(());
+    }
+    let tar_gz = File::create(&out_path)?;
+    let enc = flate2::GzBuilder::new().mtime(0).write(tar_gz, flate2::Compression::default());
+    let mut tarb = tar::Builder::new(enc);
+    for (path, name) in &entries {
+        append_normalized(&mut tarb, path, name)?;
+    }
+    let enc = tarb.into_inner()?; // GzEncoder
+    let mut inner = enc.finish()?; // File
+    inner.flush()?;
+    // compute sha256 of archive
+    let sha = sha256_file(&out_path)?;
+    println!("tar.gz sha256={} file={}", sha, out_path.display());
+    if manifest_only {
+        println!("manifest sha256={} file={}", sha256_file(&manifest_path)?, manifest_path.display());
+    }
+    if checksums {
+        let mut f = File::create("CHECKSUMS.sha256")?;
+        writeln!(f, "{}  {}", sha, out_path.file_name().unwrap().to_string_lossy())?;
+    }
+    Ok(())
+}
+
+/// `pack --manifest-only`: the manifest must be intact; the table it names is checked too
+/// when it is at hand, and its absence is only a warning since consumers regenerate it.
+fn check_manifest_only(table_path: Option<&Path>, manifest_path: &Path) -> anyhow::Result<Manifest> {
+    let mf: Manifest = serde_json::from_reader(
+        File::open(manifest_path).map_err(path_io_error(manifest_path))?,
+    )?;
+    if let Some(h) = &mf.manifest_sha256 {
+        anyhow::ensure!(*h == manifest_sha256(&mf), "manifest_sha256 mismatch: manifest was modified after gen");
+    }
+    match table_path {
+        Some(t) if t.exists() => {
+            let mut r = TableReader::open(t)?;
+            for e in r.by_ref() {
+                e.map_err(|e| anyhow::anyhow!("{}: {e}", t.display()))?;
+            }
+            let sha = hex(r.sha256().expect("trailer checked at end of iteration"));
+            anyhow::ensure!(
+                sha == mf.sha256_table_hex,
+                "{}: sha256 {sha} is not the manifest's table (sha256_table_hex={})", t.display(), mf.sha256_table_hex
+            );
+            eprintln!("pack: {} matches the manifest's sha256_table_hex; leaving it out (--manifest-only)", t.display());
+        }
+        Some(t) => eprintln!("warning: {} not found; the manifest's table sha256 is not checked", t.display()),
+        None => eprintln!("warning: no --table given; the manifest's table sha256 is not checked"),
+    }
+    Ok(mf)
+}
+
+/// Append `path` as `name` with fixed metadata (mtime/uid/gid 0, mode 0644) so identical
+/// inputs give a byte-identical archive.
+fn append_normalized<W: Write>(tarb: &mut tar::Builder<W>, path: &Path, name: &Path) -> anyhow::Result<()> {
+    let f = File::open(path).map_err(path_io_error(path))?;
+    let mut h = tar::Header::new_gnu();
+    h.set_entry_type(tar::EntryType::Regular);
+    h.set_size(f.metadata()?.len());
+    h.set_mode(0o644);
+    h.set_mtime(0);
+    h.set_uid(0);
+    h.set_gid(0);
+    tarb.append_data(&mut h, name, f)?;
+    Ok(())
+}
+
+/// `--events-json` sink: one JSON object per line, flushed as it is written so a tailing
+/// orchestrator sees it promptly. The default value is disabled and drops every event.
+#[derive(Clone, Default)]
+struct Events(Option<std::sync::Arc<std::sync::Mutex<File>>>);
+
+impl Events {
+    fn open(path: Option<&Path>) -> anyhow::Result<Events> {
+        Ok(Events(match path {
+            Some(p) => {
+                let f = File::options().create(true).append(true).open(p)
+                    .map_err(|e| anyhow::anyhow!("events {}: {e}", p.display()))?;
+                Some(std::sync::Arc::new(std::sync::Mutex::new(f)))
+            }
+            None => None,
+        }))
+    }
+
+    /// Write `{"event": kind, "ts": ..., ...fields}` as one line.
+    fn emit(&self, kind: &str, fields: serde_json::Value) -> anyhow::Result<()> {
+        let Some(sink) = &self.0 else { return Ok(()) };
+        let mut obj = serde_json::Map::new();
+        obj.insert("event".into(), kind.into());
+        obj.insert("ts".into(), chrono::Utc::now().to_rfc3339().into());
+        if let serde_json::Value::Object(m) = fields {
+            obj.extend(m);
+        }
+        let mut line = serde_json::to_vec(&obj)?;
+        line.push(b'\n');
+        let mut f = sink.lock().map_err(|_| anyhow::anyhow!("events: sink poisoned"))?;
+        f.write_all(&line)?;
+        f.flush()?;
+        Ok(())
+    }
+
+    /// Pass `r` through, recording an `error` event if it failed.
+    fn fail_on(&self, r: anyhow::Result<()>) -> anyhow::Result<()> {
+        if let Err(e) = &r {
+            let _ = self.emit("error", serde_json::json!({ "message": format!("{e:#}") }));
+        }
+        r
+    }
+}
+
+/// Write certificate gauges in the Prometheus text format (also valid OpenMetrics thanks
+/// to the `# EOF` terminator). Written to a temp file and renamed so a textfile collector
+/// never scrapes a half-written file. Threshold and eps come from the same formulas as the
+/// verdict, with the drift constant `c` in use named in their help text.
+fn write_metrics(path: &Path, k: u32, l: u32, c: f64, min_s: u32, pass: bool) -> anyhow::Result<()> {
+    let labels = format!("{{k=\"{k}\",l=\"{l}\"}}");
+    let constant = if c == log2_3() { "log2(3)".to_string() } else { c.to_string() };
+    let gauges: [(&str, String, String); 4] = [
+        ("collatz_min_s", "Minimum S over all residues".to_string(), min_s.to_string()),
+        ("collatz_threshold", format!("Strict threshold floor(l*{constant})+1"), threshold_strict(l, c)?.to_string()),
+        ("collatz_eps", format!("Drift margin min_s/l - {constant}"), drift_eps(min_s, l, c).to_string()),
+        ("collatz_pass", "1 if min_s >= threshold".to_string(), u8::from(pass).to_string()),
+    ];
+    let mut out = String::new();
+    for (name, help, value) in gauges {
+        out.push_str(&format!("# HELP {name} {help}\n# TYPE {name} gauge\n{name}{labels} {value}\n"));
+    }
+    out.push_str("# EOF\n");
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    std::fs::write(&tmp, out)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// SplitMix64: small deterministic PRNG so sampled checks are reproducible from the seed.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, n) via a 128-bit multiply-shift.
+    fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+}
+
+/// Column width for `kv` fields in human-readable output.
+const KV_WIDTH: usize = 16;
+
+/// `key=value` padded to a fixed column so fields line up across output lines.
+fn kv(key: &str, value: impl std::fmt::Display) -> String {
+    format!("{:<KV_WIDTH$}", format!("{key}={value}"))
+}
+
+/// Join padded fields into one output line without trailing padding.
+fn fields(parts: &[String]) -> String {
+    parts.concat().trim_end().to_string()
+}
+
+/// Like `kv` for a verdict: green when true, red when false. The ANSI codes are
+/// stripped by `anstream` for NO_COLOR, `--no-color` and non-TTY output.
+fn kv_pass(key: &str, pass: bool) -> String {
+    let color = if pass { anstyle::AnsiColor::Green } else { anstyle::AnsiColor::Red };
+    let style = anstyle::Style::new().fg_color(Some(color.into())).bold();
+    let text = format!("{key}={pass}");
+    let pad = KV_WIDTH.saturating_sub(text.len());
+    format!("{style}{text}{style:#}{:pad$}", "")
+}
+
+/// Largest accepted drift constant: an S entry is a u32 halving count, so a constant beyond
+/// 64 halvings per odd step only pushes the threshold out of range.
+const MAX_LOG_CONSTANT: f64 = 64.0;
+
+/// A drift constant must be positive, finite and at most `MAX_LOG_CONSTANT`.
+fn check_log_constant(c: f64) -> Result<f64, String> {
+    if !(c.is_finite() && c > 0.0) {
+        return Err(format!("constant must be positive and finite, got {c}"));
+    }
+    if c > MAX_LOG_CONSTANT {
+        return Err(format!("constant {c} is above the maximum {MAX_LOG_CONSTANT}"));
+    }
+    Ok(c)
+}
+
+/// `stats --quantile-error`: a rank error fraction in (0, 0.5).
+fn parse_quantile_error(s: &str) -> Result<f64, String> {
+    let err: f64 = s.trim().parse().map_err(|e| format!("bad error bound: {e}"))?;
+    if err > 0.0 && err < 0.5 {
+        Ok(err)
+    } else {
+        Err(format!("error bound {err} is outside (0, 0.5)"))
+    }
+}
+
+fn parse_log_constant(s: &str) -> Result<f64, String> {
+    check_log_constant(s.trim().parse().map_err(|e| format!("bad constant: {e}"))?)
+}
+
+/// Strict threshold for drift constant `c`: min_s must reach floor(l*c)+1. An error, never a
+/// wrapped value, when that does not fit the u32 an S entry is stored in.
+#[inline]
+fn threshold_strict(l: u32, c: f64) -> anyhow::Result<u32> {
+    let floor = ((l as f64)*c).floor();
+    anyhow::ensure!(floor < u32::MAX as f64, "threshold floor(L*c)+1 for L={l}, c={c} does not fit in u32");
+    Ok(floor as u32 + 1)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        s.push_str(&format!("{:02x}", b));
+    }
+    s
+}
+
+fn sha256_file(p: &Path) -> anyhow::Result<String> {
+    let f = File::open(p)?;
+    let mut r = BufReader::new(f);
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = r.read(&mut buf)?;
+        if n == 0 { break; }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+/// `bundle`: every input must be a valid, uncompressed table file, at most one per (k, l);
+/// a manifest next to it under the gen name is stored too, once it matches the table.
+fn bundle(inputs: &[PathBuf], out: &Path) -> anyhow::Result<()> {
+    let mut found: Vec<(u32, u32, Option<PathBuf>)> = Vec::with_capacity(inputs.len());
+    for p in inputs {
+        anyhow::ensure!(!is_stdin(p), "bundle: inputs must be files");
+        let mut magic = [0u8; 2];
+        File::open(p).map_err(path_io_error(p))?.read_exact(&mut magic)?;
+        anyhow::ensure!(magic != [0x1f, 0x8b], "bundle: {} is gzip-compressed; decompress it first", p.display());
+        let mut reader = TableReader::open(p)?;
+        let h = *reader.header();
+        for e in reader.by_ref() {
+            e.map_err(|e| anyhow::anyhow!("{}: {e}", p.display()))?;
+        }
+        let sha = hex(reader.sha256().expect("trailer checked at the end of iteration"));
+        anyhow::ensure!(
+            !found.iter().any(|f| (f.0, f.1) == (h.k, h.l)),
+            "bundle: more than one table for k={} l={} ({})", h.k, h.l, p.display()
+        );
+        let mp = p.parent().unwrap_or(Path::new("")).join(default_manifest_name(h.k, h.l));
+        let manifest = if mp.exists() {
+            let mf: Manifest = serde_json::from_reader(File::open(&mp).map_err(path_io_error(&mp))?)
+                .map_err(|e| anyhow::anyhow!("{}: {e}", mp.display()))?;
+            anyhow::ensure!(mf.sha256_table_hex == sha, "bundle: {} does not describe {}", mp.display(), p.display());
+            Some(mp)
+        } else {
+            None
+        };
+        found.push((h.k, h.l, manifest));
+    }
+    let items: Vec<collatz_cert::BundleInput> = inputs.iter().zip(&found)
+        .map(|(table, (k, l, manifest))| collatz_cert::BundleInput { k: *k, l: *l, table, manifest: manifest.as_deref() })
+        .collect();
+    let entries = collatz_cert::write_bundle(out, &items)?;
+    eprintln!("bundle: {} ({} entries)", out.display(), entries.len());
+    for e in &entries {
+        let manifest = if e.manifest_len > 0 { "with manifest" } else { "no manifest" };
+        eprintln!("  {}", fields(&[kv("k", e.k), kv("l", e.l), kv("offset", e.table_offset), kv("bytes", e.table_len), manifest.to_string()]));
+    }
+    Ok(())
+}
+
+/// Entries of `bundle` that `--k/--l` selects: all of them, or the one asked for.
+fn bundle_selection(b: &collatz_cert::Bundle, path: &Path, only: Option<(u32, u32)>) -> anyhow::Result<Vec<collatz_cert::BundleEntry>> {
+    match only {
+        Some((k, l)) => match b.find(k, l) {
+            Some(e) => Ok(vec![e.clone()]),
+            None => anyhow::bail!("{}: no entry for k={k} l={l}", path.display()),
+        },
+        None => Ok(b.entries.clone()),
+    }
+}
+
+fn unbundle(path: &Path, only: Option<(u32, u32)>, out_dir: &Path) -> anyhow::Result<()> {
+    let mut b = collatz_cert::Bundle::open(path)?;
+    std::fs::create_dir_all(out_dir).map_err(path_io_error(out_dir))?;
+    for e in bundle_selection(&b, path, only)? {
+        let table = out_dir.join(default_table_name(e.k, e.l));
+        std::fs::write(&table, b.read_table(&e)?).map_err(path_io_error(&table))?;
+        eprintln!("unbundle: {}", table.display());
+        if let Some(json) = b.read_manifest(&e)? {
+            let manifest = out_dir.join(default_manifest_name(e.k, e.l));
+            std::fs::write(&manifest, json).map_err(path_io_error(&manifest))?;
+            eprintln!("unbundle: {}", manifest.display());
+        }
+    }
+    Ok(())
+}
+
+/// `verify-bundle`: structural checks only, as `verify --structural-only` plus the manifest
+/// link; `unbundle` then `verify` recomputes an entry.
+fn verify_bundle(path: &Path, only: Option<(u32, u32)>) -> anyhow::Result<()> {
+    let mut b = collatz_cert::Bundle::open(path)?;
+    eprintln!("verify-bundle: {} ({} entries), directory sha256 ok", path.display(), b.entries.len());
+    let mut failed = 0;
+    for e in bundle_selection(&b, path, only)? {
+        let r = (|| -> anyhow::Result<&'static str> {
+            let data = b.read_table(&e)?;
+            let h = Header::parse(&data)?;
+            anyhow::ensure!((h.k, h.l) == (e.k, e.l), "table header has k={} l={}", h.k, h.l);
+            let body = match h.body_len() {
+                Some(n) => {
+                    anyhow::ensure!(data.len() == 32 + n + 32, "bad table length");
+                    &data[32..32 + n]
+                }
+                None => &data[32..data.len() - 32],
+            };
+            let trailer = &data[data.len() - 32..];
+            anyhow::ensure!(Sha256::digest(body).as_slice() == trailer, "table sha256 mismatch");
+            let Some(json) = b.read_manifest(&e)? else { return Ok("no manifest") };
+            let mf: Manifest = serde_json::from_slice(&json)?;
+            anyhow::ensure!((mf.k, mf.l) == (e.k, e.l), "manifest has k={} l={}", mf.k, mf.l);
+            anyhow::ensure!(mf.sha256_table_hex == hex(trailer), "manifest sha256 mismatch");
+            if let Some(h) = &mf.manifest_sha256 {
+                anyhow::ensure!(*h == manifest_sha256(&mf), "manifest_sha256 mismatch: manifest was modified after gen");
+            }
+            Ok("manifest ok")
+        })();
+        match r {
+            Ok(note) => anstream::eprintln!("  {}", fields(&[kv("k", e.k), kv("l", e.l), kv_pass("ok", true), note.to_string()])),
+            Err(err) => {
+                failed += 1;
+                anstream::eprintln!("  {}", fields(&[kv("k", e.k), kv("l", e.l), kv_pass("ok", false), format!("{err:#}")]));
+            }
+        }
+    }
+    anyhow::ensure!(failed == 0, "verify-bundle: {failed} entr{} failed", if failed == 1 { "y" } else { "ies" });
+    Ok(())
 }
@@ -4,6 +4,13 @@ use tempfile::tempdir;
 use std::fs::File;
 use std::io::{Write, Read};
 
+fn bin() -> Command {
+    // the caller's shell defaults must not leak into the tests
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("collatz_cert"));
+    cmd.env_remove("COLLATZ_K").env_remove("COLLATZ_L");
+    cmd
+}
+
 fn collatz_s_sum(k: u32, l: u32, idx: usize) -> u32 {
     let mask: u64 = (1u64 << k) - 1;
     let mut m = ((idx as u64) << 1) | 1;
@@ -17,20 +24,43 @@ fn collatz_s_sum(k: u32, l: u32, idx: usize) -> u32 {
     s.min(u32::MAX as u64) as u32
 }
 
+#[path = "../benches/common/s_sum.rs"]
+mod bench_loop;
+
+#[test]
+fn bench_loop_matches_library() {
+    // the bench times its own copy of the loop; it must stay the library's definition
+    for k in [2u32, 3, 8, 13, 20, 28] {
+        let mask = (1u64 << k) - 1;
+        let count = 1u64 << (k - 1);
+        for l in [0u32, 1, 7, 64, 256] {
+            let idxs = (0..count.min(512)).chain((count.saturating_sub(64)..count).step_by(7));
+            for idx in idxs {
+                assert_eq!(
+                    bench_loop::collatz_s_sum(k, l, idx as usize),
+                    collatz_cert::s_sum(mask, l, idx),
+                    "k={k} l={l} idx={idx}"
+                );
+                assert_eq!(collatz_s_sum(k, l, idx as usize), collatz_cert::s_sum(mask, l, idx), "k={k} l={l} idx={idx}");
+            }
+        }
+    }
+}
+
 #[test]
 fn gen_v2_and_verify_roundtrip_small() -> Result<(), Box<dyn std::error::Error>> {
     let dir = tempdir()?;
     let dir_path = dir.path();
 
     // Generate v2 with defaults
-    let mut cmd = Command::cargo_bin("collatz_cert")?;
+    let mut cmd = bin();
     cmd.current_dir(dir_path)
         .args(["gen", "--k", "4", "--l", "8", "--threads", "2"])
         .assert()
         .success();
 
     // Verify produced files
-    let mut cmd2 = Command::cargo_bin("collatz_cert")?;
+    let mut cmd2 = bin();
     cmd2.current_dir(dir_path)
         .args([
             "verify", "--k", "4", "--l", "8",
@@ -59,44 +89,18 @@ fn verify_v1_synthetic_small() -> Result<(), Box<dyn std::error::Error>> {
     let dir = tempdir()?;
     let dir_path = dir.path();
 
-    // Prepare table (u16) and header+trailer
-    let mut table_bytes: Vec<u8> = Vec::with_capacity(count * 2);
-    let mut min_s = u32::MAX;
-    for idx in 0..count {
-        let s_i = collatz_s_sum(k, l, idx);
-        min_s = min_s.min(s_i);
-        let v = (s_i as u16).to_le_bytes();
-        table_bytes.extend_from_slice(&v);
-    }
-
-    // Header: magic, ver=1, k, l, count, reserved
-    let mut file_bytes: Vec<u8> = Vec::new();
-    file_bytes.extend_from_slice(b"CALT");
-    file_bytes.extend_from_slice(1u32.to_le_bytes().as_slice());
-    file_bytes.extend_from_slice(k.to_le_bytes().as_slice());
-    file_bytes.extend_from_slice(l.to_le_bytes().as_slice());
-    file_bytes.extend_from_slice((count as u64).to_le_bytes().as_slice());
-    file_bytes.extend_from_slice(&[0u8; 8]);
-    file_bytes.extend_from_slice(&table_bytes);
-
-    // Trailer: sha256(table_bytes)
-    let mut hasher = sha2::Sha256::new();
-    use sha2::Digest;
-    hasher.update(&table_bytes);
-    let digest = hasher.finalize();
-    file_bytes.extend_from_slice(&digest);
-
-    // Write table file
+    // Write the v1 (u16 entries) table; the builder adds header and sha256 trailer
+    let values: Vec<u32> = (0..count).map(|idx| collatz_s_sum(k, l, idx)).collect();
+    let min_s = *values.iter().min().unwrap();
     let table_path = dir_path.join("table_v1_k4_l8.bin");
-    let mut f = File::create(&table_path)?;
-    f.write_all(&file_bytes)?;
+    let digest = collatz_cert::TableBuilder::new(k, l).version(1).write_file(&table_path, values)?;
 
     // Manifest JSON with required fields
     let thr = ((l as f64) * (3f64.log2())).floor() as u32 + 1;
-    let pass = (min_s as u32) >= thr;
+    let pass = min_s >= thr;
     let eps = (min_s as f64) / (l as f64) - 3f64.log2();
     let mut hex = String::new();
-    for b in digest.as_slice() { hex.push_str(&format!("{:02x}", b)); }
+    for b in digest { hex.push_str(&format!("{:02x}", b)); }
     let manifest = serde_json::json!({
         "k": k,
         "l": l,
@@ -120,7 +124,7 @@ fn verify_v1_synthetic_small() -> Result<(), Box<dyn std::error::Error>> {
     mf.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
 
     // Run verify against v1 synthetic files
-    let mut cmd = Command::cargo_bin("collatz_cert")?;
+    let mut cmd = bin();
     cmd.current_dir(dir_path)
         .args([
             "verify", "--k", "4", "--l", "8",
@@ -133,3 +137,2368 @@ fn verify_v1_synthetic_small() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn gen_resumes_from_checkpoint_to_identical_table() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let dir_path = dir.path();
+
+    // Reference run without checkpointing
+    bin().current_dir(dir_path)
+        .args(["gen", "--k", "4", "--l", "8", "--threads", "2",
+               "--out-table", "ref.bin", "--out-manifest", "ref.json"])
+        .assert()
+        .success();
+    let mut reference = Vec::new();
+    File::open(dir_path.join("ref.bin"))?.read_to_end(&mut reference)?;
+
+    // Simulate a crash after 3 entries: truncated body plus a torn partial entry
+    let done: usize = 3;
+    let mut partial = reference[..32 + done * 4].to_vec();
+    partial.extend_from_slice(&[0xde, 0xad]);
+    File::create(dir_path.join("table.bin"))?.write_all(&partial)?;
+    let min_s = (0..done).map(|i| collatz_s_sum(4, 8, i)).min().unwrap();
+    let cp = serde_json::json!({
+        "k": 4, "l": 8, "file_ver": 2, "table": "table.bin",
+        "next_index": done as u64, "min_s": min_s,
+    });
+    File::create(dir_path.join("gen.ckpt"))?.write_all(cp.to_string().as_bytes())?;
+
+    bin().current_dir(dir_path)
+        .args(["gen", "--k", "4", "--l", "8", "--threads", "2",
+               "--out-table", "table.bin", "--out-manifest", "cert.json",
+               "--checkpoint", "gen.ckpt"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("resuming at index 3"));
+
+    let mut resumed = Vec::new();
+    File::open(dir_path.join("table.bin"))?.read_to_end(&mut resumed)?;
+    assert_eq!(resumed, reference);
+    let a: serde_json::Value = serde_json::from_reader(File::open(dir_path.join("ref.json"))?)?;
+    let b: serde_json::Value = serde_json::from_reader(File::open(dir_path.join("cert.json"))?)?;
+    assert_eq!(a["min_s"], b["min_s"]);
+    assert_eq!(a["sha256_table_hex"], b["sha256_table_hex"]);
+    assert!(!dir_path.join("gen.ckpt").exists());
+
+    // a checkpoint taken under another drift constant or residue set is not resumed
+    for (key, value) in [("log_constant", serde_json::json!(1.5)), ("include_even", serde_json::json!(true))] {
+        File::create(dir_path.join("table.bin"))?.write_all(&partial)?;
+        let mut cp = cp.clone();
+        cp[key] = value;
+        File::create(dir_path.join("gen.ckpt"))?.write_all(cp.to_string().as_bytes())?;
+        bin().current_dir(dir_path)
+            .args(["gen", "--k", "4", "--l", "8", "--threads", "2",
+                   "--out-table", "table.bin", "--out-manifest", "cert.json",
+                   "--checkpoint", "gen.ckpt"])
+            .assert()
+            .success()
+            .stderr(predicates::str::contains("checkpoint: parameters differ, starting fresh"));
+        assert_eq!(std::fs::read(dir_path.join("table.bin"))?, reference, "{key}");
+    }
+    Ok(())
+}
+
+#[test]
+fn verify_reads_k_l_from_header_when_omitted() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let dir_path = dir.path();
+    bin().current_dir(dir_path)
+        .args(["gen", "--k", "4", "--l", "8", "--threads", "2"])
+        .assert()
+        .success();
+
+    bin().current_dir(dir_path)
+        .args(["verify", "--table", "table_k4_l8_v2.bin", "--manifest", "cert_k4_l8_v2.json"])
+        .assert()
+        .success();
+    bin().current_dir(dir_path)
+        .args(["verify", "--l", "9", "--table", "table_k4_l8_v2.bin", "--manifest", "cert_k4_l8_v2.json"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("K/L mismatch"));
+    Ok(())
+}
+
+#[test]
+fn config_file_supplies_output_dir() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let dir_path = dir.path();
+    std::fs::create_dir(dir_path.join("out"))?;
+    File::create(dir_path.join("collatz.toml"))?
+        .write_all(b"# shared defaults\nthreads = 2\noutput_dir = \"out\"\n")?;
+
+    bin().current_dir(dir_path)
+        .args(["--config", "collatz.toml", "gen", "--k", "4", "--l", "8", "--threads", "1"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("threads=1"));
+    assert!(dir_path.join("out/table_k4_l8_v2.bin").exists());
+    assert!(dir_path.join("out/cert_k4_l8_v2.json").exists());
+    Ok(())
+}
+
+#[test]
+fn doctored_count_is_rejected() -> Result<(), Box<dyn std::error::Error>> {
+    // Valid body/trailer for 4 entries, but the header claims k=24
+    let table_bytes: Vec<u8> = (0..4u32).flat_map(|v| v.to_le_bytes()).collect();
+    let mut file_bytes: Vec<u8> = Vec::new();
+    file_bytes.extend_from_slice(b"CALT");
+    file_bytes.extend_from_slice(&2u32.to_le_bytes());
+    file_bytes.extend_from_slice(&24u32.to_le_bytes());
+    file_bytes.extend_from_slice(&8u32.to_le_bytes());
+    file_bytes.extend_from_slice(&4u64.to_le_bytes());
+    file_bytes.extend_from_slice(&[0u8; 8]);
+    file_bytes.extend_from_slice(&table_bytes);
+    use sha2::Digest;
+    file_bytes.extend_from_slice(&sha2::Sha256::digest(&table_bytes));
+
+    let dir = tempdir()?;
+    let table_path = dir.path().join("doctored.bin");
+    File::create(&table_path)?.write_all(&file_bytes)?;
+
+    bin().args(["stats", "--table"]).arg(&table_path)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("requires count=2^(k-1)=8388608"));
+    bin().args(["verify", "--structural-only", "--table"]).arg(&table_path)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("count=4 but k=24"));
+    Ok(())
+}
+
+fn read_v2_values(path: &std::path::Path) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+    let count = u64::from_le_bytes(data[16..24].try_into()?) as usize;
+    Ok(data[32..32 + 4 * count].chunks_exact(4)
+        .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+        .collect())
+}
+
+#[test]
+fn gen_include_even_covers_all_residues() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let dir_path = dir.path();
+    bin().current_dir(dir_path)
+        .args(["gen", "--k", "5", "--l", "8", "--threads", "2",
+               "--include-even", "--out-table", "all.bin", "--out-manifest", "all.json"])
+        .assert()
+        .success();
+    bin().current_dir(dir_path)
+        .args(["verify", "--table", "all.bin", "--manifest", "all.json"])
+        .assert()
+        .success();
+
+    let all = read_v2_values(&dir_path.join("all.bin"))?;
+    assert_eq!(all.len(), 32);
+    for (r, &s) in all.iter().enumerate().skip(1) {
+        // odd part m of r = 2^v * m contributes its own S after v halvings
+        let v = r.trailing_zeros();
+        let m = r >> v;
+        assert_eq!(s, v + collatz_s_sum(5, 8, m >> 1), "residue {r}");
+    }
+    let mf: serde_json::Value = serde_json::from_reader(File::open(dir_path.join("all.json"))?)?;
+    assert_eq!(mf["include_even"], true);
+    assert_eq!(mf["residue_form"], "all");
+    assert_eq!(mf["modulus_bits"], 5);
+    assert_eq!(mf["count"], 32);
+    Ok(())
+}
+
+#[test]
+fn gzipped_table_is_read_transparently() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let dir_path = dir.path();
+    bin().current_dir(dir_path)
+        .args(["gen", "--k", "4", "--l", "8", "--threads", "2"])
+        .assert()
+        .success();
+
+    let mut raw = Vec::new();
+    File::open(dir_path.join("table_k4_l8_v2.bin"))?.read_to_end(&mut raw)?;
+    let mut enc = flate2::write::GzEncoder::new(
+        File::create(dir_path.join("table_k4_l8_v2.bin.gz"))?,
+        flate2::Compression::default(),
+    );
+    enc.write_all(&raw)?;
+    enc.finish()?;
+
+    bin().current_dir(dir_path)
+        .args(["verify", "--table", "table_k4_l8_v2.bin.gz", "--manifest", "cert_k4_l8_v2.json"])
+        .assert()
+        .success();
+    bin().current_dir(dir_path)
+        .args(["stats", "--table", "table_k4_l8_v2.bin.gz"])
+        .assert()
+        .success();
+    Ok(())
+}
+
+#[test]
+fn stats_snaps_excess_bins_to_integer_width() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let dir_path = dir.path();
+    bin().current_dir(dir_path)
+        .args(["gen", "--k", "6", "--l", "8", "--threads", "2"])
+        .assert()
+        .success();
+    let values = read_v2_values(&dir_path.join("table_k6_l8_v2.bin"))?;
+    let (mn, mx) = (*values.iter().min().unwrap(), *values.iter().max().unwrap());
+
+    for bins in ["1000", "100000"] {
+        bin().current_dir(dir_path)
+            .args(["stats", "--table", "table_k6_l8_v2.bin", "--bins", bins, "--out-csv", "h.csv"])
+            .assert()
+            .success()
+            .stderr(predicates::str::contains("integer-width bins"));
+        let csv = std::fs::read_to_string(dir_path.join("h.csv"))?;
+        let rows: Vec<(f64, f64, usize)> = csv.lines().skip(1).map(|r| {
+            let c: Vec<&str> = r.split(',').collect();
+            (c[0].parse().unwrap(), c[1].parse().unwrap(), c[2].parse().unwrap())
+        }).collect();
+        assert_eq!(rows.len(), (mx - mn + 1) as usize);
+        for (i, &(lo, hi, _)) in rows.iter().enumerate() {
+            assert_eq!(lo, (mn as usize + i) as f64);
+            assert_eq!(hi - lo, 1.0);
+        }
+        assert_eq!(rows.iter().map(|r| r.2).sum::<usize>(), values.len());
+        let at_max = values.iter().filter(|&&v| v == mx).count();
+        assert_eq!(rows.last().unwrap().2, at_max);
+    }
+    Ok(())
+}
+
+#[test]
+fn verify_reports_lowest_mismatching_index() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "8", "--l", "16", "--threads", "2"])
+        .assert()
+        .success();
+
+    // Bump two entries and re-seal the trailer so only the value check fails
+    let table_path = dir.path().join("table_k8_l16_v2.bin");
+    let mut bytes = std::fs::read(&table_path)?;
+    let body_end = bytes.len() - 32;
+    for idx in [90usize, 17] {
+        let off = 32 + idx * 4;
+        let v = u32::from_le_bytes(bytes[off..off + 4].try_into()?) + 1;
+        bytes[off..off + 4].copy_from_slice(&v.to_le_bytes());
+    }
+    use sha2::Digest;
+    let digest = sha2::Sha256::digest(&bytes[32..body_end]);
+    bytes[body_end..].copy_from_slice(&digest);
+    std::fs::write(&table_path, &bytes)?;
+
+    let expected = collatz_s_sum(8, 16, 17);
+    bin().current_dir(dir.path())
+        .args([
+            "verify", "--table", "table_k8_l16_v2.bin",
+            "--manifest", "cert_k8_l16_v2.json", "--threads", "4",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(format!(
+            "value mismatch: index 17 recomputed S={expected} stored S={}", expected + 1
+        )));
+    Ok(())
+}
+
+#[test]
+fn table_reader_streams_entries_and_checks_trailer() -> Result<(), Box<dyn std::error::Error>> {
+    use collatz_cert::TableReader;
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "6", "--l", "8", "--threads", "1"])
+        .assert()
+        .success();
+    let table_path = dir.path().join("table_k6_l8_v2.bin");
+    let expected = read_v2_values(&table_path)?;
+
+    let buffered: Vec<(u64, u32)> = TableReader::open(&table_path)?.collect::<Result<_, _>>()?;
+    let mapped: Vec<(u64, u32)> = unsafe { TableReader::open_mmap(&table_path)? }.collect::<Result<_, _>>()?;
+    let want: Vec<(u64, u32)> = expected.iter().enumerate().map(|(i, &s)| (i as u64, s)).collect();
+    assert_eq!(buffered, want);
+    assert_eq!(mapped, want);
+
+    // A flipped trailer byte surfaces as a final Err item after every entry
+    let mut bytes = std::fs::read(&table_path)?;
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xff;
+    std::fs::write(&table_path, &bytes)?;
+    let items: Vec<_> = TableReader::open(&table_path)?.collect();
+    assert_eq!(items.len(), want.len() + 1);
+    let err = items.last().unwrap().as_ref().unwrap_err();
+    assert!(err.to_string().contains("sha256 mismatch"));
+    Ok(())
+}
+
+#[test]
+fn table_format_is_little_endian_on_any_host() -> Result<(), Box<dyn std::error::Error>> {
+    use collatz_cert::{write_header, Header, TableReader};
+    // Header for k=3 l=5, count=4, written out byte by byte in little-endian order
+    let head: [u8; 32] = [
+        b'C', b'A', b'L', b'T', 2, 0, 0, 0, 3, 0, 0, 0, 5, 0, 0, 0,
+        4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+    let h = Header::decode(&head)?;
+    assert_eq!((h.ver, h.k, h.l, h.count, h.flags), (2, 3, 5, 4, 0));
+    let mut written = Vec::new();
+    write_header(&mut written, &h)?;
+    assert_eq!(written, head);
+
+    let body: [u8; 16] = [0x34, 0x12, 0, 0, 0x78, 0x56, 0x34, 0x12, 1, 0, 0, 0, 0, 0, 0, 0x80];
+    let mut file_bytes = head.to_vec();
+    file_bytes.extend_from_slice(&body);
+    use sha2::Digest;
+    file_bytes.extend_from_slice(&sha2::Sha256::digest(body));
+    let dir = tempdir()?;
+    let path = dir.path().join("le.bin");
+    std::fs::write(&path, &file_bytes)?;
+    let values: Vec<u32> = TableReader::open(&path)?.map(|e| e.map(|(_, s)| s)).collect::<Result<_, _>>()?;
+    assert_eq!(values, [0x1234, 0x1234_5678, 1, 0x8000_0000], "target_endian={}",
+        if cfg!(target_endian = "big") { "big" } else { "little" });
+    Ok(())
+}
+
+#[test]
+fn stats_max_entries_uses_labelled_strided_sample() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let dir_path = dir.path();
+    bin().current_dir(dir_path)
+        .args(["gen", "--k", "8", "--l", "16", "--threads", "2"])
+        .assert()
+        .success();
+    let values = read_v2_values(&dir_path.join("table_k8_l16_v2.bin"))?;
+    let sample_min = values.iter().step_by(16).min().unwrap();
+
+    bin().current_dir(dir_path)
+        .args(["stats", "--table", "table_k8_l16_v2.bin", "--max-entries", "8", "--out-csv", "h.csv"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("sample: 8 of 128 entries (stride 16)"))
+        .stderr(predicates::str::contains(format!("min_S={sample_min} ")));
+    let csv = std::fs::read_to_string(dir_path.join("h.csv"))?;
+    let total: usize = csv.lines().skip(1).map(|r| r.rsplit(',').next().unwrap().parse::<usize>().unwrap()).sum();
+    assert_eq!(total, 8);
+    Ok(())
+}
+
+#[test]
+fn canonical_manifest_bytes_ignore_field_order() -> Result<(), Box<dyn std::error::Error>> {
+    use collatz_cert::{canonical_json_bytes, canonical_manifest_bytes, Manifest};
+    #[derive(serde::Serialize)]
+    struct A { k: u32, eps: f64, name: String, pass: bool }
+    #[derive(serde::Serialize)]
+    struct B { pass: bool, name: String, eps: f64, k: u32 }
+    let a = canonical_json_bytes(&A { k: 24, eps: 0.1416, name: "t".into(), pass: true })?;
+    let b = canonical_json_bytes(&B { pass: true, name: "t".into(), eps: 0.1416, k: 24 })?;
+    assert_eq!(a, b);
+    assert_eq!(String::from_utf8(a)?, r#"{"eps":0.1416,"k":24,"name":"t","pass":true}"#);
+
+    // A real manifest canonicalizes the same whichever order its keys arrive in
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "4", "--l", "8", "--threads", "1"])
+        .assert()
+        .success();
+    let text = std::fs::read_to_string(dir.path().join("cert_k4_l8_v2.json"))?;
+    let m: Manifest = serde_json::from_str(&text)?;
+    let reversed: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str::<serde_json::Map<_, _>>(&text)?.into_iter().rev().collect();
+    assert_eq!(canonical_manifest_bytes(&m), canonical_json_bytes(&reversed)?);
+    Ok(())
+}
+
+#[test]
+fn verify_rejects_edited_manifest_via_manifest_sha256() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "6", "--l", "8", "--threads", "1"])
+        .assert()
+        .success();
+    let mf_path = dir.path().join("cert_k6_l8_v2.json");
+    let mut v: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&mf_path)?)?;
+    assert_eq!(v["manifest_sha256"].as_str().map(str::len), Some(64));
+    v["notes"] = serde_json::Value::from("added later");
+    std::fs::write(&mf_path, serde_json::to_string_pretty(&v)?)?;
+
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "table_k6_l8_v2.bin", "--manifest", "cert_k6_l8_v2.json"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("manifest_sha256 mismatch"));
+    Ok(())
+}
+
+#[test]
+fn pack_tar_root_nests_entries() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "4", "--l", "8", "--threads", "1"])
+        .assert()
+        .success();
+    bin().current_dir(dir.path())
+        .args([
+            "pack", "--table", "table_k4_l8_v2.bin", "--manifest", "cert_k4_l8_v2.json",
+            "--out", "c.tar.gz", "--tar-root", "cert_k4_l8",
+        ])
+        .assert()
+        .success();
+    let gz = flate2::read::GzDecoder::new(File::open(dir.path().join("c.tar.gz"))?);
+    let mut names = Vec::new();
+    for e in tar::Archive::new(gz).entries()? {
+        names.push(e?.path()?.to_string_lossy().into_owned());
+    }
+    assert_eq!(names, ["cert_k4_l8/table_k4_l8_v2.bin", "cert_k4_l8/cert_k4_l8_v2.json"]);
+    Ok(())
+}
+
+#[test]
+fn pack_is_byte_reproducible() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "4", "--l", "8", "--threads", "1"])
+        .assert()
+        .success();
+    let pack = |out: &str| {
+        bin().current_dir(dir.path())
+            .args(["pack", "--table", "table_k4_l8_v2.bin", "--manifest", "cert_k4_l8_v2.json", "--out", out])
+            .assert()
+            .success();
+    };
+    pack("a.tar.gz");
+    // a different mtime on an input must not leak into the archive
+    File::options().write(true).open(dir.path().join("table_k4_l8_v2.bin"))?
+        .set_modified(std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000))?;
+    pack("b.tar.gz");
+    assert_eq!(std::fs::read(dir.path().join("a.tar.gz"))?, std::fs::read(dir.path().join("b.tar.gz"))?);
+    Ok(())
+}
+
+#[test]
+fn extend_from_matches_fresh_full_run() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    for even in [false, true] {
+        let extra: &[&str] = if even { &["--include-even"] } else { &[] };
+        let gen = |l: &str, out: &str, more: &[&str]| {
+            bin().current_dir(dir.path())
+                .args(["gen", "--k", "9", "--l", l, "--threads", "2", "--out-table", out, "--out-manifest"])
+                .arg(format!("{out}.json"))
+                .args(extra)
+                .args(more)
+                .assert()
+                .success();
+        };
+        gen("24", "base.bin", &["--with-state"]);
+        gen("61", "ext.bin", &["--extend-from", "base.bin"]);
+        gen("61", "fresh.bin", &["--with-state"]);
+        assert_eq!(std::fs::read(dir.path().join("ext.bin"))?, std::fs::read(dir.path().join("fresh.bin"))?);
+
+        bin().current_dir(dir.path())
+            .args(["verify", "--table", "ext.bin", "--manifest", "ext.bin.json"])
+            .assert()
+            .success();
+    }
+    Ok(())
+}
+
+#[test]
+fn gen_dump_worst_traces_lowest_argmin() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "20", "--threads", "3", "--dump-worst", "worst.csv"])
+        .assert()
+        .success();
+    let values = read_v2_values(&dir.path().join("table_k10_l20_v2.bin"))?;
+    let min_s = *values.iter().min().unwrap();
+    let argmin = values.iter().position(|&v| v == min_s).unwrap();
+
+    let csv = std::fs::read_to_string(dir.path().join("worst.csv"))?;
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some(format!("# k=10 l=20 index={argmin} residue={} s={min_s}", 2 * argmin + 1).as_str()));
+    assert_eq!(lines.next(), Some("step,m,e,s,next"));
+    let rows: Vec<Vec<u64>> = lines.map(|r| r.split(',').map(|c| c.parse().unwrap()).collect()).collect();
+    assert_eq!(rows.len(), 20);
+    assert_eq!(rows[0][1], 2 * argmin as u64 + 1);
+    assert_eq!(rows.iter().map(|r| r[2]).sum::<u64>(), min_s as u64);
+    for w in rows.windows(2) {
+        assert_eq!(w[0][4], w[1][1]);
+    }
+    Ok(())
+}
+
+#[test]
+fn compare_manifest_separates_correctness_from_environment() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "6", "--l", "8", "--threads", "1"])
+        .assert()
+        .success();
+    let text = std::fs::read_to_string(dir.path().join("cert_k6_l8_v2.json"))?;
+    let mut v: serde_json::Value = serde_json::from_str(&text)?;
+    v["gen_ts"] = serde_json::Value::from("1970-01-01T00:00:00+00:00");
+    std::fs::write(dir.path().join("env.json"), v.to_string())?;
+    let mut max_s = v.clone();
+    max_s["max_s"] = (max_s["max_s"].as_u64().unwrap() + 1).into();
+    std::fs::write(dir.path().join("max_s.json"), max_s.to_string())?;
+    v["pass"] = serde_json::Value::from(!v["pass"].as_bool().unwrap());
+    std::fs::write(dir.path().join("bad.json"), v.to_string())?;
+
+    let verify = |other: &str| {
+        let mut cmd = bin();
+        cmd.current_dir(dir.path()).args([
+            "verify", "--table", "table_k6_l8_v2.bin", "--manifest", "cert_k6_l8_v2.json", "--compare-manifest", other,
+        ]);
+        cmd
+    };
+    verify("env.json").assert()
+        .success()
+        .stderr(predicates::str::contains("correctness: identical"))
+        .stderr(predicates::str::contains("environment: 1 field(s) differ"));
+    verify("bad.json").assert()
+        .failure()
+        .stderr(predicates::str::contains("correctness: 1 field(s) differ"))
+        .stderr(predicates::str::contains("    pass: "));
+    // the recomputed distribution statistics are correctness fields too
+    verify("max_s.json").assert()
+        .failure()
+        .stderr(predicates::str::contains("correctness: 1 field(s) differ"))
+        .stderr(predicates::str::contains("    max_s: "));
+    Ok(())
+}
+
+#[test]
+fn sweep_skip_existing_only_regenerates_missing_or_failing() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let sweep = || {
+        let mut cmd = bin();
+        cmd.current_dir(dir.path())
+            .args(["sweep", "--k", "4,6", "--l", "8", "--threads", "1", "--out-dir", "out", "--skip-existing"]);
+        cmd
+    };
+    sweep().assert()
+        .success()
+        .stderr(predicates::str::contains("sweep: 2 pairs: 0 skipped, 2 generated (0 after failed verify)"));
+    sweep().assert()
+        .success()
+        .stderr(predicates::str::contains("sweep: 2 pairs: 2 skipped, 0 generated"));
+
+    // a corrupted table is regenerated, the intact one is kept
+    let table = dir.path().join("out/table_k6_l8_v2.bin");
+    let mut bytes = std::fs::read(&table)?;
+    bytes[40] ^= 1;
+    std::fs::write(&table, &bytes)?;
+    sweep().assert()
+        .success()
+        .stderr(predicates::str::contains("sweep: 2 pairs: 1 skipped, 1 generated (1 after failed verify)"));
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "out/table_k6_l8_v2.bin", "--manifest", "out/cert_k6_l8_v2.json"])
+        .assert()
+        .success();
+    Ok(())
+}
+
+#[test]
+fn gen_records_max_s_and_mean_s_checked_by_verify() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--threads", "3"])
+        .assert()
+        .success();
+    let values = read_v2_values(&dir.path().join("table_k10_l32_v2.bin"))?;
+    let mf_path = dir.path().join("cert_k10_l32_v2.json");
+    let mut v: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&mf_path)?)?;
+    assert_eq!(v["max_s"].as_u64(), values.iter().max().map(|&m| m as u64));
+    let mean = values.iter().map(|&s| s as u64).sum::<u64>() as f64 / values.len() as f64;
+    assert_eq!(v["mean_s"].as_f64(), Some(mean));
+
+    // without the manifest hash, a doctored max_s is caught by the recompute cross-check
+    v.as_object_mut().unwrap().remove("manifest_sha256");
+    v["max_s"] = serde_json::Value::from(v["max_s"].as_u64().unwrap() + 1);
+    std::fs::write(&mf_path, v.to_string())?;
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "table_k10_l32_v2.bin", "--manifest", "cert_k10_l32_v2.json", "--threads", "3"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("manifest max_s mismatch"));
+    Ok(())
+}
+
+#[test]
+fn events_json_streams_start_progress_complete() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--threads", "2", "--events-json", "ev.jsonl"])
+        .assert()
+        .success();
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "table_k10_l32_v2.bin", "--manifest", "cert_k10_l32_v2.json",
+               "--events-json", "ev.jsonl"])
+        .assert()
+        .success();
+    let events: Vec<serde_json::Value> = std::fs::read_to_string(dir.path().join("ev.jsonl"))?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    let kinds: Vec<&str> = events.iter().map(|e| e["event"].as_str().unwrap()).collect();
+    let gen_end = kinds.iter().position(|&k| k == "complete").unwrap();
+    assert_eq!(kinds[0], "start");
+    assert_eq!(events[0]["cmd"], "gen");
+    assert!(kinds[1..gen_end].iter().all(|&k| k == "progress"));
+    assert_eq!(events[gen_end - 1]["fraction"].as_f64(), Some(1.0));
+    assert_eq!(events[gen_end]["threshold"], 51);
+    assert_eq!(kinds[gen_end + 1], "start");
+    assert_eq!(events[gen_end + 1]["cmd"], "verify");
+    assert_eq!(kinds.last(), Some(&"complete"));
+    assert_eq!(events.last().unwrap()["min_s"], events[gen_end]["min_s"]);
+    assert_eq!(events.last().unwrap()["pass"], events[gen_end]["pass"]);
+    Ok(())
+}
+
+#[test]
+fn validate_manifest_checks_self_consistency_without_table() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    std::fs::remove_file(dir.path().join("t.bin"))?;
+    let out = bin().current_dir(dir.path())
+        .args(["validate-manifest", "--manifest", "m.json"])
+        .output()?;
+    let err = String::from_utf8(out.stderr)?;
+    assert!(out.status.success(), "{err}");
+    assert!(err.contains("eps: ok") && err.contains("table not checked"), "{err}");
+
+    let mf_path = dir.path().join("m.json");
+    let mut v: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&mf_path)?)?;
+    v["eps"] = serde_json::Value::from(v["eps"].as_f64().unwrap() + 0.5);
+    std::fs::write(&mf_path, v.to_string())?;
+    let out = bin().current_dir(dir.path())
+        .args(["validate-manifest", "--manifest", "m.json"])
+        .output()?;
+    let err = String::from_utf8(out.stderr)?;
+    assert!(!out.status.success());
+    assert!(err.contains("threshold: ok") && err.contains("eps: inconsistent"), "{err}");
+    assert!(err.contains("1 inconsistent field(s)"), "{err}");
+    Ok(())
+}
+
+#[test]
+fn stats_exact_histogram_is_same_dense_or_sparse() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let values = read_v2_values(&dir.path().join("t.bin"))?;
+    let mut csvs = Vec::new();
+    for (cap, mode) in [("1000", "dense"), ("10", "sparse")] {
+        let csv = dir.path().join(format!("exact_{cap}.csv"));
+        let out = bin().current_dir(dir.path())
+            .args(["stats", "--table", "t.bin", "--exact-histogram", "--max-value-cap", cap, "--out-csv"])
+            .arg(&csv)
+            .output()?;
+        assert!(out.status.success());
+        assert!(String::from_utf8(out.stderr)?.contains(&format!("exact histogram: {mode}")));
+        csvs.push(std::fs::read_to_string(&csv)?);
+    }
+    assert_eq!(csvs[0], csvs[1]);
+    let rows: Vec<(u32, usize)> = csvs[0].lines().skip(1)
+        .map(|r| { let (v, n) = r.split_once(',').unwrap(); (v.parse().unwrap(), n.parse().unwrap()) })
+        .collect();
+    assert_eq!(rows.iter().map(|&(_, n)| n).sum::<usize>(), values.len());
+    for &(v, n) in &rows {
+        assert_eq!(values.iter().filter(|&&s| s == v).count(), n);
+    }
+    Ok(())
+}
+
+#[test]
+fn gen_require_pass_fails_but_keeps_files() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    // K=10 L=32 misses the threshold (min_S=43 < thr=51)
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--require-pass"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--require-pass: pass=false (min_S=43 < thr=51)"));
+    assert!(dir.path().join("table_k10_l32_v2.bin").exists());
+    let mf: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.path().join("cert_k10_l32_v2.json"))?)?;
+    assert_eq!(mf["pass"], false);
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32"])
+        .assert()
+        .success();
+    Ok(())
+}
+
+#[test]
+fn verify_and_stats_read_table_from_stdin() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let table = std::fs::read(dir.path().join("t.bin"))?;
+    assert_cmd::Command::from_std(bin()).current_dir(dir.path())
+        .args(["verify", "--table", "-", "--manifest", "m.json"])
+        .write_stdin(table.clone())
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("verify: min_S=43"));
+
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    gz.write_all(&table)?;
+    assert_cmd::Command::from_std(bin()).current_dir(dir.path())
+        .args(["stats", "--table", "-"])
+        .write_stdin(gz.finish()?)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("stats: K=10 L=32 ver=2 count=512"));
+
+    let mut bad = table;
+    let n = bad.len();
+    bad[n - 1] ^= 1;
+    assert_cmd::Command::from_std(bin()).current_dir(dir.path())
+        .args(["verify", "--table", "-", "--manifest", "m.json"])
+        .write_stdin(bad)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("sha256 mismatch"));
+    Ok(())
+}
+
+#[test]
+fn pack_dry_run_lists_plan_without_writing() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let table_len = std::fs::metadata(dir.path().join("t.bin"))?.len();
+    bin().current_dir(dir.path())
+        .args(["pack", "--table", "t.bin", "--manifest", "m.json", "--tar-root", "cert", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("would write cert_k10_l32_v2.tar.gz"))
+        .stdout(predicates::str::contains(format!("cert/t.bin  {table_len} bytes")));
+    assert!(!dir.path().join("cert_k10_l32_v2.tar.gz").exists());
+
+    bin().current_dir(dir.path())
+        .args(["pack", "--table", "t.bin", "--manifest", "missing.json", "--dry-run"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("missing.json"));
+
+    let mut bytes = std::fs::read(dir.path().join("t.bin"))?;
+    bytes[40] ^= 1;
+    std::fs::write(dir.path().join("t.bin"), bytes)?;
+    bin().current_dir(dir.path())
+        .args(["pack", "--table", "t.bin", "--manifest", "m.json", "--dry-run"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("sha256 mismatch"));
+    bin().current_dir(dir.path())
+        .args(["pack", "--table", "t.bin", "--manifest", "m.json", "--dry-run", "--no-hash"])
+        .assert()
+        .success();
+    Ok(())
+}
+
+#[test]
+fn pow3_bit_length_is_exact() {
+    for l in 0..=80u32 {
+        let p = 3u128.pow(l);
+        assert_eq!(collatz_cert::pow3_bit_length(l), 128 - p.leading_zeros() as u64, "l={l}");
+    }
+    // 3^l built up limb by limb, through the convergent denominators 15601 and 31867 where
+    // l*log2(3) is closest to an integer
+    let mut limbs = vec![1u32];
+    for l in 1..=31867u32 {
+        let mut carry = 0u64;
+        for d in limbs.iter_mut() {
+            let v = *d as u64 * 3 + carry;
+            *d = v as u32;
+            carry = v >> 32;
+        }
+        if carry > 0 {
+            limbs.push(carry as u32);
+        }
+        let bits = 32 * (limbs.len() as u64 - 1) + (32 - limbs.last().unwrap().leading_zeros()) as u64;
+        assert_eq!(collatz_cert::pow3_bit_length(l), bits, "l={l}");
+    }
+    // the closest u32 case, 397573379*log2(3) = 630138897 - 1.5e-10, and the largest l
+    // return at once
+    assert_eq!(collatz_cert::pow3_bit_length(397_573_379), 630_138_897);
+    assert_eq!(collatz_cert::pow3_bit_length(u32::MAX), 6_807_362_105);
+}
+
+#[test]
+fn gen_counts_every_entry_as_negative_drift_at_l_0() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    // s = 0 and eps = -c for every entry of an empty block, whatever the constant
+    for (m, extra) in [("log2_3.json", &[][..]), ("c15.json", &["--log-constant", "1.5"][..])] {
+        bin().current_dir(dir.path())
+            .args(["gen", "--k", "6", "--l", "0", "--out-table", &format!("{m}.bin"), "--out-manifest", m])
+            .args(extra)
+            .assert()
+            .success();
+        let v: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.path().join(m))?)?;
+        assert_eq!(v["neg_drift_count"], 32, "{m}");
+        bin().current_dir(dir.path())
+            .args(["verify", "--table", &format!("{m}.bin"), "--manifest", m])
+            .assert()
+            .success();
+    }
+    Ok(())
+}
+
+#[test]
+fn gen_counts_negative_drift_checked_by_verify() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--threads", "2"])
+        .assert()
+        .success();
+    let values = read_v2_values(&dir.path().join("table_k10_l32_v2.bin"))?;
+    let p = 3u128.pow(32);
+    let expect = values.iter().filter(|&&s| (1u128 << s) < p).count() as u64;
+    assert!(expect > 0);
+    let mf_path = dir.path().join("cert_k10_l32_v2.json");
+    let mut v: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&mf_path)?)?;
+    assert_eq!(v["neg_drift_count"].as_u64(), Some(expect));
+
+    v.as_object_mut().unwrap().remove("manifest_sha256");
+    v["neg_drift_count"] = serde_json::Value::from(expect - 1);
+    std::fs::write(&mf_path, v.to_string())?;
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "table_k10_l32_v2.bin", "--manifest", "cert_k10_l32_v2.json"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("manifest neg_drift_count mismatch"));
+    Ok(())
+}
+
+#[test]
+fn gen_throttle_paces_output_without_changing_it() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "fast.bin", "--out-manifest", "fast.json"])
+        .assert()
+        .success();
+    // 512 entries at 256/s: two one-second chunks
+    let t0 = std::time::Instant::now();
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "slow.bin", "--out-manifest", "slow.json",
+               "--throttle-eps", "256"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("throttle: target 256 entries/s"));
+    assert!(t0.elapsed() >= std::time::Duration::from_millis(1900));
+    assert_eq!(std::fs::read(dir.path().join("fast.bin"))?, std::fs::read(dir.path().join("slow.bin"))?);
+    Ok(())
+}
+
+#[test]
+fn gen_profile_reports_phase_breakdown() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--profile-json", "profile.json"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("profile: compute="));
+    let v: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.path().join("profile.json"))?)?;
+    let part = |key: &str| v[key].as_f64().unwrap();
+    let sum = part("compute_s") + part("hash_s") + part("io_s") + part("other_s");
+    assert!(part("compute_s") > 0.0);
+    assert!((sum - part("total_s")).abs() < 1e-6, "{v}");
+    Ok(())
+}
+
+#[test]
+fn compact_rle_table_round_trips_through_readers_and_verify() -> Result<(), Box<dyn std::error::Error>> {
+    use collatz_cert::{read_table_bytes, TableReader, VER_RLE};
+    use sha2::Digest;
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    bin().current_dir(dir.path())
+        .args(["compact", "--table", "t.bin", "--out", "t4.bin", "--manifest", "m.json", "--out-manifest", "m4.json"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("compact: 512 entries -> "));
+    let values = read_v2_values(&dir.path().join("t.bin"))?;
+    let (h, table) = read_table_bytes(&dir.path().join("t4.bin"))?;
+    assert_eq!(h.ver, VER_RLE);
+    assert_eq!(table, values);
+    let mapped = unsafe { TableReader::open_mmap(&dir.path().join("t4.bin"))? };
+    assert_eq!(mapped.map(|e| e.map(|(_, s)| s)).collect::<anyhow::Result<Vec<u32>>>()?, values);
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "t4.bin", "--manifest", "m4.json"])
+        .assert()
+        .success();
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "t4.bin", "--structural-only"])
+        .assert()
+        .success();
+
+    // a zero-length run is rejected even though the trailer matches the doctored body
+    let mut bytes = std::fs::read(dir.path().join("t4.bin"))?;
+    let end = bytes.len() - 32;
+    bytes[36..40].copy_from_slice(&0u32.to_le_bytes());
+    let digest = sha2::Sha256::digest(&bytes[32..end]);
+    bytes[end..].copy_from_slice(&digest);
+    std::fs::write(dir.path().join("bad.bin"), bytes)?;
+    bin().current_dir(dir.path())
+        .args(["stats", "--table", "bad.bin"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("bad rle body: empty run"));
+    Ok(())
+}
+
+#[test]
+fn verify_refuses_incompatible_algo_ver() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let path = dir.path().join("t.bin");
+    let mut bytes = std::fs::read(&path)?;
+    assert_eq!(u16::from_le_bytes([bytes[28], bytes[29]]), collatz_cert::ALGO_VER);
+    // 0 marks tables from before the field existed: same definition, still accepted
+    for (algo, ok) in [(0u16, true), (collatz_cert::ALGO_VER + 1, false)] {
+        bytes[28..30].copy_from_slice(&algo.to_le_bytes());
+        std::fs::write(&path, &bytes)?;
+        let assert = bin().current_dir(dir.path())
+            .args(["verify", "--table", "t.bin", "--manifest", "m.json"])
+            .assert();
+        if ok {
+            assert.success();
+        } else {
+            assert.failure().stderr(predicates::str::contains("is incompatible with this build"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn gen_interrupt_cleans_up_and_exits_130() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    // throttled so the signal lands mid-run: 2048 entries at 256/s
+    let interrupt = |extra: &[&str]| -> Result<std::process::Output, Box<dyn std::error::Error>> {
+        let child = bin().current_dir(dir.path())
+            .args(["gen", "--k", "12", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json",
+                   "--throttle-eps", "256"])
+            .args(extra)
+            .stderr(std::process::Stdio::piped())
+            .spawn()?;
+        std::thread::sleep(std::time::Duration::from_millis(1500));
+        unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGINT) };
+        Ok(child.wait_with_output()?)
+    };
+
+    let out = interrupt(&[])?;
+    let err = String::from_utf8(out.stderr)?;
+    assert_eq!(out.status.code(), Some(130), "{err}");
+    assert!(err.contains("removed partial t.bin") && err.contains("interrupted"), "{err}");
+    assert!(!dir.path().join("t.bin").exists());
+    assert!(!dir.path().join("m.json").exists());
+
+    // with a checkpoint the partial table is progress, not garbage: it is kept and resumes
+    let out = interrupt(&["--checkpoint", "cp.json"])?;
+    let err = String::from_utf8(out.stderr)?;
+    assert_eq!(out.status.code(), Some(130), "{err}");
+    assert!(err.contains("kept t.bin and cp.json"), "{err}");
+    assert!(!dir.path().join("m.json").exists());
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "12", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json",
+               "--checkpoint", "cp.json"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("checkpoint: resuming at index"));
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin", "--manifest", "m.json"])
+        .assert()
+        .success();
+    Ok(())
+}
+
+#[test]
+fn stats_baseline_flags_moved_figures() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    bin().current_dir(dir.path())
+        .args(["stats", "--table", "t.bin", "--out-json", "prev.json"])
+        .assert()
+        .success();
+    bin().current_dir(dir.path())
+        .args(["stats", "--table", "t.bin", "--baseline", "prev.json"])
+        .assert()
+        .success();
+
+    let path = dir.path().join("prev.json");
+    let mut v: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+    v["stddev_s"] = serde_json::Value::from(v["stddev_s"].as_f64().unwrap() + 0.01);
+    std::fs::write(&path, v.to_string())?;
+    bin().current_dir(dir.path())
+        .args(["stats", "--table", "t.bin", "--baseline", "prev.json"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("CHANGED"))
+        .stderr(predicates::str::contains("1 figure(s) moved beyond tolerance"));
+    bin().current_dir(dir.path())
+        .args(["stats", "--table", "t.bin", "--baseline", "prev.json", "--tolerance", "0.1"])
+        .assert()
+        .success();
+    Ok(())
+}
+
+#[test]
+fn drift_iter_and_par_drift_match_reference() {
+    use rayon::prelude::*;
+    let (k, l) = (9, 40);
+    let c = 3f64.log2();
+    let items: Vec<(u64, u32, f64)> = collatz_cert::drift_iter(k, l).collect();
+    assert_eq!(items.len(), 1 << (k - 1));
+    for &(i, s, eps) in &items {
+        assert_eq!(s, collatz_s_sum(k, l, i as usize), "index {i}");
+        assert_eq!(eps, s as f64 / l as f64 - c);
+    }
+    let mut par: Vec<(u64, u32, f64)> = collatz_cert::par_drift(k, l).collect();
+    par.sort_by_key(|&(i, _, _)| i);
+    assert_eq!(par, items);
+    let min = collatz_cert::par_drift(k, l).map(|(_, s, _)| s).min();
+    assert_eq!(min, items.iter().map(|&(_, s, _)| s).min());
+}
+
+#[test]
+fn gen_l0_is_the_identity_case() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "6", "--l", "0"])
+        .assert()
+        .success();
+    let values = read_v2_values(&dir.path().join("table_k6_l0_v2.bin"))?;
+    assert_eq!(values, vec![0; 32]);
+    let mf: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.path().join("cert_k6_l0_v2.json"))?)?;
+    assert_eq!(mf["min_s"], 0);
+    assert_eq!(mf["threshold"], 1);
+    assert_eq!(mf["pass"], false);
+    assert_eq!(mf["eps"].as_f64(), Some(-3f64.log2()));
+    assert_eq!(mf["neg_drift_count"], 32);
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "table_k6_l0_v2.bin", "--manifest", "cert_k6_l0_v2.json"])
+        .assert()
+        .success();
+    Ok(())
+}
+
+#[test]
+fn gen_dump_failing_writes_sorted_capped_indices() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--threads", "3", "--dump-failing", "failing.txt"])
+        .assert()
+        .success();
+    let expect: Vec<u64> = (0..512).filter(|&i| collatz_s_sum(10, 32, i) < 51).map(|i| i as u64).collect();
+    assert!(expect.len() > 3);
+    let read = |name: &str| -> Result<Vec<u64>, Box<dyn std::error::Error>> {
+        Ok(std::fs::read_to_string(dir.path().join(name))?.lines().map(str::parse).collect::<Result<_, _>>()?)
+    };
+    assert_eq!(read("failing.txt")?, expect);
+
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--dump-failing", "capped.txt", "--max-failing", "3"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(format!("wrote the lowest 3 of {} failing indices", expect.len())));
+    assert_eq!(read("capped.txt")?, expect[..3]);
+
+    // c=1 puts the threshold at 33, below min_S=43
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--log-constant", "1", "--dump-failing", "none.txt"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("no residues below thr=33 (pass=true)"));
+    assert_eq!(std::fs::read(dir.path().join("none.txt"))?, b"");
+    Ok(())
+}
+
+#[test]
+fn verify_direct_io_reads_plain_and_gzip_tables() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let (raw, _) = collatz_cert::read_table_file_direct(&dir.path().join("t.bin"))?;
+    assert_eq!(raw, std::fs::read(dir.path().join("t.bin"))?);
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    gz.write_all(&raw)?;
+    std::fs::write(dir.path().join("t.bin.gz"), gz.finish()?)?;
+    for table in ["t.bin", "t.bin.gz"] {
+        bin().current_dir(dir.path())
+            .args(["verify", "--table", table, "--manifest", "m.json", "--direct"])
+            .assert()
+            .success();
+    }
+    Ok(())
+}
+
+#[test]
+fn constant_prints_truncated_log2_3() -> Result<(), Box<dyn std::error::Error>> {
+    let digits = |n: &str| -> Result<String, Box<dyn std::error::Error>> {
+        let out = bin().args(["constant", "--digits", n]).output()?;
+        assert!(out.status.success());
+        Ok(String::from_utf8(out.stdout)?.trim_end().to_string())
+    };
+    let d60 = digits("60")?;
+    assert_eq!(d60, "1.584962500721156181453738943947816508759814407692481060455752");
+    assert!(d60.starts_with(&format!("{:.15}", collatz_cert::log2_3())));
+    assert!(digits("200")?.starts_with(&digits("50")?));
+    Ok(())
+}
+
+#[test]
+fn gen_rejects_a_log_constant_out_of_range() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "4", "--l", "8", "--log-constant", "1e10"])
+        .assert()
+        .code(2)
+        .stderr(predicates::str::contains("above the maximum 64"));
+    assert!(!dir.path().join("table_k4_l8_v2.bin").exists());
+    Ok(())
+}
+
+#[test]
+fn metrics_help_names_the_constant_in_use() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    for (name, extra) in [("default", &[][..]), ("c15", &["--log-constant", "1.5"][..])] {
+        bin().current_dir(dir.path())
+            .args(["gen", "--k", "6", "--l", "8", "--out-table", &format!("{name}.bin"), "--out-manifest", &format!("{name}.json"),
+                   "--metrics", &format!("{name}.prom")])
+            .args(extra)
+            .assert()
+            .success();
+    }
+    let default = std::fs::read_to_string(dir.path().join("default.prom"))?;
+    assert!(default.contains("# HELP collatz_threshold Strict threshold floor(l*log2(3))+1"), "{default}");
+    assert!(default.contains("collatz_threshold{k=\"6\",l=\"8\"} 13"), "{default}");
+    let custom = std::fs::read_to_string(dir.path().join("c15.prom"))?;
+    assert!(custom.contains("# HELP collatz_threshold Strict threshold floor(l*1.5)+1"), "{custom}");
+    assert!(custom.contains("# HELP collatz_eps Drift margin min_s/l - 1.5"), "{custom}");
+    assert!(custom.contains("collatz_threshold{k=\"6\",l=\"8\"} 13"), "{custom}");
+    assert!(!custom.contains("log2(3)"), "{custom}");
+    // verify writes the same help from the manifest's constant
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "c15.bin", "--manifest", "c15.json", "--metrics", "v.prom"])
+        .assert()
+        .success();
+    assert_eq!(std::fs::read_to_string(dir.path().join("v.prom"))?, custom);
+    Ok(())
+}
+
+#[test]
+fn threshold_rejects_an_l_whose_threshold_overflows() -> Result<(), Box<dyn std::error::Error>> {
+    bin()
+        .args(["threshold", "--l", "3000000000"])
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("does not fit in u32"));
+    bin()
+        .args(["threshold", "--l", "256", "--log-constant", "1e10"])
+        .assert()
+        .code(2)
+        .stderr(predicates::str::contains("above the maximum 64"));
+    // the largest L the default constant allows still prints a threshold; one more does not
+    bin()
+        .args(["threshold", "--l", "2709822657"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("thr=4294967295"));
+    bin().args(["threshold", "--l", "2709822658"]).assert().code(1);
+    Ok(())
+}
+
+#[test]
+fn verify_checks_modulus_bits_and_residue_form() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let mf_path = dir.path().join("m.json");
+    let orig: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&mf_path)?)?;
+    assert_eq!(orig["modulus_bits"], 10);
+    assert_eq!(orig["residue_form"], "odd");
+    for (field, value, msg) in [
+        ("residue_form", serde_json::json!("all"), "residue_form mismatch"),
+        ("residue_form", serde_json::json!("even"), "residue_form mismatch: manifest=even"),
+        ("modulus_bits", serde_json::json!(11), "modulus_bits mismatch"),
+    ] {
+        let mut v = orig.clone();
+        v[field] = value;
+        // drop the seal so the field checks themselves are reached
+        v.as_object_mut().unwrap().remove("manifest_sha256");
+        std::fs::write(&mf_path, v.to_string())?;
+        bin().current_dir(dir.path())
+            .args(["verify", "--table", "t.bin", "--manifest", "m.json"])
+            .assert()
+            .failure()
+            .stderr(predicates::str::contains(msg));
+    }
+    Ok(())
+}
+
+#[test]
+fn stats_above_reports_upper_tail() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let values = read_v2_values(&dir.path().join("t.bin"))?;
+    let tail: Vec<u32> = values.iter().copied().filter(|&s| s >= 60).collect();
+    let mean = tail.iter().map(|&s| s as f64).sum::<f64>() / tail.len() as f64;
+    bin().current_dir(dir.path())
+        .args(["stats", "--table", "t.bin", "--above", "60"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(format!("S>=60={}", tail.len())))
+        .stderr(predicates::str::contains(format!("frac={:.6}", tail.len() as f64 / values.len() as f64)))
+        .stderr(predicates::str::contains(format!("mean(tail)={mean:.3}")));
+    bin().current_dir(dir.path())
+        .args(["stats", "--table", "t.bin", "--tail", "1000"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("S>=1000=0"))
+        .stderr(predicates::str::contains("tail is empty"));
+    Ok(())
+}
+
+#[test]
+fn verify_build_compares_build_metadata() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "8", "--l", "16", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let verify = || bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin", "--manifest", "m.json", "--verify-build"])
+        .assert()
+        .success();
+    verify()
+        .stderr(predicates::str::contains("build_rustc: same"))
+        .stderr(predicates::str::contains("sha256_exec_hex: identical binary"));
+
+    let mf_path = dir.path().join("m.json");
+    let mut v: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&mf_path)?)?;
+    v.as_object_mut().unwrap().remove("manifest_sha256");
+    v["build_rustc"] = "rustc 0.0.0".into();
+    v["build_git_rev"] = "unknown".into();
+    std::fs::write(&mf_path, v.to_string())?;
+    verify()
+        .stderr(predicates::str::contains("build_rustc: differs"))
+        .stderr(predicates::str::contains("build_git_rev: cannot compare"))
+        .stderr(predicates::str::contains("warning: verifying binary is not the generating build"));
+    Ok(())
+}
+
+#[test]
+fn export_streams_index_s_csv() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "8", "--l", "16", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let values = read_v2_values(&dir.path().join("t.bin"))?;
+    let rows: String = values.iter().enumerate().map(|(i, s)| format!("{i},{s}\n")).collect();
+    let out = bin().current_dir(dir.path()).args(["export", "--table", "t.bin"]).output()?;
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8(out.stdout)?, format!("index,s\n{rows}"));
+    bin().current_dir(dir.path())
+        .args(["export", "--table", "t.bin", "--out", "t.csv", "--no-header"])
+        .assert()
+        .success();
+    assert_eq!(std::fs::read_to_string(dir.path().join("t.csv"))?, rows);
+
+    // a bad trailer is only seen after the last row; the partial CSV must not survive
+    let mut bytes = std::fs::read(dir.path().join("t.bin"))?;
+    let last = bytes.len() - 1;
+    bytes[last] ^= 1;
+    std::fs::write(dir.path().join("bad.bin"), bytes)?;
+    bin().current_dir(dir.path())
+        .args(["export", "--table", "bad.bin", "--out", "bad.csv"])
+        .assert()
+        .failure();
+    assert!(!dir.path().join("bad.csv").exists());
+    Ok(())
+}
+
+#[test]
+fn gen_pipeline_writes_identical_table() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    for (name, extra) in [("serial", None), ("pipe", Some("--pipeline"))] {
+        let (table, manifest) = (format!("{name}.bin"), format!("{name}.json"));
+        let mut args = vec!["gen", "--k", "12", "--l", "16", "--out-table", &table, "--out-manifest", &manifest];
+        args.extend(extra);
+        bin().current_dir(dir.path()).args(&args).assert().success();
+    }
+    assert_eq!(std::fs::read(dir.path().join("serial.bin"))?, std::fs::read(dir.path().join("pipe.bin"))?);
+    let out = bin().current_dir(dir.path())
+        .args(["gen", "--k", "12", "--l", "16", "--pipeline", "--table-stdout", "--out-manifest", "stdout.json"])
+        .output()?;
+    assert!(out.status.success());
+    assert_eq!(out.stdout, std::fs::read(dir.path().join("serial.bin"))?);
+    Ok(())
+}
+
+#[test]
+fn trace_summary_matches_full_trace_and_table() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let values = read_v2_values(&dir.path().join("t.bin"))?;
+    for idx in [0u64, 7, 300, 511] {
+        let i = idx.to_string();
+        let full = bin().args(["trace", "--k", "10", "--l", "32", "--index", &i]).output()?;
+        assert!(full.status.success());
+        let rows: Vec<Vec<u64>> = String::from_utf8(full.stdout)?
+            .lines()
+            .skip(2)
+            .map(|line| line.split(',').map(|x| x.parse().unwrap()).collect())
+            .collect();
+        assert_eq!(rows.len(), 32);
+        let drops = rows.iter().filter(|r| r[2] >= 2).count();
+        let max_e = rows.iter().map(|r| r[2]).max().unwrap();
+        let (s, last) = (rows[31][3], rows[31][4]);
+        assert_eq!(s, values[idx as usize] as u64);
+
+        let out = bin().args(["trace", "--k", "10", "--l", "32", "--index", &i, "--summary"]).output()?;
+        let text = String::from_utf8(out.stdout)?;
+        for want in [format!("S={s} "), format!("e>=2={drops} "), format!("max_e={max_e} "), format!("final={last}")] {
+            assert!(text.contains(&want), "{want} not in {text}");
+        }
+    }
+    bin().args(["trace", "--k", "10", "--l", "32", "--index", "512"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("out of range"));
+    Ok(())
+}
+
+#[test]
+fn manifest_schema_covers_every_manifest_field() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "8", "--l", "16", "--note", "n", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let mf: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.path().join("m.json"))?)?;
+    let out = bin().arg("manifest-schema").output()?;
+    assert!(out.status.success());
+    let schema: serde_json::Value = serde_json::from_slice(&out.stdout)?;
+    assert_eq!(schema, collatz_cert::manifest_schema());
+
+    // a fresh gen writes every field, so the key sets must be equal
+    let props = schema["properties"].as_object().unwrap();
+    let mut want: Vec<&String> = mf.as_object().unwrap().keys().collect();
+    let mut have: Vec<&String> = props.keys().collect();
+    want.sort();
+    have.sort();
+    assert_eq!(have, want);
+    for name in schema["required"].as_array().unwrap() {
+        assert!(props.contains_key(name.as_str().unwrap()), "{name}");
+    }
+    // required fields are exactly those an older manifest cannot omit
+    let mut minimal = mf.as_object().unwrap().clone();
+    minimal.retain(|key, _| schema["required"].as_array().unwrap().iter().any(|r| r == key));
+    serde_json::from_value::<collatz_cert::Manifest>(serde_json::Value::Object(minimal))?;
+    Ok(())
+}
+
+#[test]
+fn format_spec_offsets_match_written_header() -> Result<(), Box<dyn std::error::Error>> {
+    use collatz_cert::{Header, HEADER_FIELDS};
+    let h = Header {
+        magic: *b"CALT", ver: 0x0403_0201, k: 0x1413_1211, l: 0x2423_2221, count: 0x3837_3635_3433_3231,
+        flags: 0x4443_4241, algo_ver: 0x5251, _reserved: [0x61, 0x62],
+    };
+    let mut bytes = Vec::new();
+    collatz_cert::write_header(&mut bytes, &h)?;
+    assert_eq!(HEADER_FIELDS.iter().map(|f| f.size).sum::<usize>(), bytes.len());
+    let mut next = 0;
+    for f in &HEADER_FIELDS {
+        assert_eq!(f.offset, next, "{}", f.name);
+        next += f.size;
+        let want: Vec<u8> = match f.name {
+            "magic" => h.magic.to_vec(),
+            "ver" => h.ver.to_le_bytes().to_vec(),
+            "k" => h.k.to_le_bytes().to_vec(),
+            "l" => h.l.to_le_bytes().to_vec(),
+            "count" => h.count.to_le_bytes().to_vec(),
+            "flags" => h.flags.to_le_bytes().to_vec(),
+            "algo_ver" => h.algo_ver.to_le_bytes().to_vec(),
+            "reserved" => h._reserved.to_vec(),
+            other => panic!("unlisted header field {other}"),
+        };
+        assert_eq!(&bytes[f.offset..f.offset + f.size], want.as_slice(), "{}", f.name);
+    }
+
+    let out = bin().arg("format-spec").output()?;
+    assert!(out.status.success());
+    let text = String::from_utf8(out.stdout)?;
+    assert_eq!(text, collatz_cert::format_spec());
+    assert!(text.contains("  16      8     count"), "{text}");
+    assert!(text.contains("ver=4: (s: u32, run: u32) pairs"), "{text}");
+    Ok(())
+}
+
+#[test]
+fn verify_explain_narrates_threshold_and_margin() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin", "--manifest", "m.json", "--explain"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("thr = floor(L * c) + 1 = floor(32 * 1.584963) + 1 = floor(50.718800) + 1 = 51."))
+        .stderr(predicates::str::contains("min_S = 43 < thr = 51"))
+        .stderr(predicates::str::contains("eps = min_S/L - c = 43/32 - 1.584963 = -0.241213."))
+        .stderr(predicates::str::contains("Verdict: FAIL"));
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin", "--manifest", "m.json", "--explain", "--sample", "10"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn bench_start_index_wraps_over_all_entries() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "12", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let min_s = read_v2_values(&dir.path().join("t.bin"))?.into_iter().min().unwrap();
+    // the minimum is only found if the wrapped pass still visits every index
+    for start in ["0", "1", "2047"] {
+        bin().args(["bench", "--k", "12", "--l", "32", "--iters", "1", "--start-index", start])
+            .assert()
+            .success()
+            .stderr(predicates::str::contains(format!("min_S={min_s}")));
+    }
+    bin().args(["bench", "--k", "12", "--l", "32", "--start-index", "2048"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("out of range"));
+    Ok(())
+}
+
+#[test]
+fn stats_no_hash_skips_trailer_with_warning() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "8", "--l", "16", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let mut bytes = std::fs::read(dir.path().join("t.bin"))?;
+    let last = bytes.len() - 1;
+    bytes[last] ^= 1;
+    std::fs::write(dir.path().join("t.bin"), bytes)?;
+    bin().current_dir(dir.path())
+        .args(["stats", "--table", "t.bin"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("sha256 mismatch"));
+    bin().current_dir(dir.path())
+        .args(["stats", "--table", "t.bin", "--no-hash"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("warning: sha256 not checked"));
+    Ok(())
+}
+
+#[test]
+fn eps_tolerance_and_exact_eps_govern_the_eps_check() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "8", "--l", "16", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let mf_path = dir.path().join("m.json");
+    let orig: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&mf_path)?)?;
+    let shift_eps = |delta: f64| -> Result<(), Box<dyn std::error::Error>> {
+        let mut v = orig.clone();
+        v.as_object_mut().unwrap().remove("manifest_sha256");
+        v["eps"] = serde_json::Value::from(orig["eps"].as_f64().unwrap() + delta);
+        std::fs::write(&mf_path, v.to_string())?;
+        Ok(())
+    };
+    let validate = |extra: &[&str]| {
+        bin().current_dir(dir.path()).args(["validate-manifest", "--manifest", "m.json"]).args(extra).assert()
+    };
+
+    // a last-digit difference passes the default tolerance but not --exact-eps
+    shift_eps(1e-15)?;
+    validate(&[]).success();
+    validate(&["--exact-eps"]).failure().stderr(predicates::str::contains("eps compared exactly"));
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin", "--manifest", "m.json", "--exact-eps"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("manifest eps mismatch"));
+
+    shift_eps(1e-10)?;
+    validate(&[]).failure();
+    validate(&["--eps-tolerance", "1e-9"]).success().stderr(predicates::str::contains("note: eps tolerance 1e-9"));
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin", "--manifest", "m.json", "--eps-tolerance", "1e-9"])
+        .assert()
+        .success();
+    Ok(())
+}
+
+#[test]
+fn verify_all_summarises_a_directory() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    // c=1 makes k=6 l=8 pass; the default constant fails k=8 l=16
+    bin().current_dir(dir.path()).args(["gen", "--k", "6", "--l", "8", "--log-constant", "1"]).assert().success();
+    bin().current_dir(dir.path()).args(["gen", "--k", "8", "--l", "16"]).assert().success();
+    std::fs::copy(dir.path().join("table_k8_l16_v2.bin"), dir.path().join("table_k9_l16_v2.bin"))?;
+
+    let out = bin().current_dir(dir.path()).args(["verify-all", "--dir", ".", "--jobs", "2"]).output()?;
+    assert!(!out.status.success());
+    let text = String::from_utf8(out.stdout)?;
+    let row = |name: &str| text.lines().find(|line| line.ends_with(name) || line.contains(&format!("{name}:"))).unwrap_or("").to_string();
+    assert!(row("table_k6_l8_v2.bin").contains("pass=true"), "{text}");
+    assert!(row("table_k8_l16_v2.bin").contains("pass=false"), "{text}");
+    assert!(row("table_k9_l16_v2.bin").contains("missing"), "{text}");
+    assert!(text.contains("3 checked, 2 failed"), "{text}");
+
+    bin().current_dir(dir.path())
+        .args(["verify-all", "--dir", ".", "--filter", "table_k6_*"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("1 checked, 0 failed"));
+    Ok(())
+}
+
+#[test]
+fn export_raw_le_writes_bare_values() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "8", "--l", "16", "--with-state", "--out-table", "t3.bin", "--out-manifest", "m3.json"])
+        .assert()
+        .success();
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "8", "--l", "16", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let values = read_v2_values(&dir.path().join("t.bin"))?;
+    let want: Vec<u8> = values.iter().flat_map(|s| s.to_le_bytes()).collect();
+    // ver=3 end residues are dropped: only S values go out
+    for table in ["t.bin", "t3.bin"] {
+        let out = bin().current_dir(dir.path()).args(["export", "--table", table, "--format", "raw-le"]).output()?;
+        assert!(out.status.success());
+        assert_eq!(out.stdout, want, "{table}");
+    }
+    Ok(())
+}
+
+#[test]
+fn fixed_points_match_brute_force_and_show_in_stats() -> Result<(), Box<dyn std::error::Error>> {
+    for k in 2..=16u32 {
+        let mask = (1u64 << k) - 1;
+        let brute: Vec<(u64, u32)> = (0..1u64 << (k - 1))
+            .map(|i| (i << 1) | 1)
+            .filter_map(|m| {
+                let (e, next) = collatz_cert::steps(mask, 1, m);
+                (next == m).then_some((m, e))
+            })
+            .collect();
+        assert_eq!(collatz_cert::fixed_points(k), brute, "k={k}");
+    }
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let values = read_v2_values(&dir.path().join("t.bin"))?;
+    bin().current_dir(dir.path())
+        .args(["stats", "--table", "t.bin"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("fixed point: residue=1 (index 0) e=2 every step, S=2*L=64"))
+        .stderr(predicates::str::contains(format!("all-ones residue 1023 (index 511): S={}", values[511])));
+    Ok(())
+}
+
+#[test]
+fn verify_repeat_runs_agree() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let min_s = read_v2_values(&dir.path().join("t.bin"))?.into_iter().min().unwrap();
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin", "--manifest", "m.json", "--repeat", "3", "--threads", "4"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(format!("repeat: 3 runs agree (min_S={min_s} ")));
+    Ok(())
+}
+
+#[test]
+fn summary_line_is_one_tab_separated_record() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let out = bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json", "--summary-line"])
+        .output()?;
+    assert!(out.status.success());
+    let mf: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.path().join("m.json"))?)?;
+    let sha8 = &mf["sha256_table_hex"].as_str().unwrap()[..8];
+    let verify = bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin", "--manifest", "m.json", "--summary-line"])
+        .output()?;
+    assert!(verify.status.success());
+    for stdout in [out.stdout, verify.stdout] {
+        let text = String::from_utf8(stdout)?;
+        assert_eq!(text.lines().count(), 1, "{text}");
+        let cols: Vec<&str> = text.trim_end().split('\t').collect();
+        assert_eq!(cols.len(), 9, "{text}");
+        assert_eq!(&cols[..7], ["10", "32", "512", "43", "51", "false", "-0.241213"]);
+        assert!(cols[7].parse::<u64>().is_ok());
+        assert_eq!(cols[8], sha8);
+    }
+    Ok(())
+}
+
+#[test]
+fn verify_expect_min_s_without_manifest() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let verify = |extra: &[&str]| bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin"])
+        .args(extra)
+        .assert();
+    verify(&["--expect-min-s", "43"])
+        .success()
+        .stderr(predicates::str::contains("expect-min-s: recomputed min_S=43 as expected"));
+    verify(&["--expect-min-s", "44"])
+        .failure()
+        .stderr(predicates::str::contains("--expect-min-s mismatch: expected=44 computed=43"));
+    verify(&["--expect-min-s", "43", "--manifest", "m.json"]).success();
+    verify(&["--expect-min-s", "42", "--manifest", "m.json"])
+        .failure()
+        .stderr(predicates::str::contains("--expect-min-s mismatch"));
+    // without either, there is nothing to check the table against
+    verify(&[]).failure();
+    // neither path recomputes min_S, so the assertion is refused rather than skipped
+    verify(&["--expect-min-s", "999", "--structural-only"])
+        .code(2)
+        .stderr(predicates::str::contains("cannot be used with"));
+    bin().current_dir(dir.path())
+        .args(["verify", "--no-table", "--manifest", "m.json", "--expect-min-s", "999"])
+        .assert()
+        .code(2);
+    Ok(())
+}
+
+#[test]
+fn verify_min_margin_gates_on_clearance_above_threshold() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let verify = |extra: &[&str]| bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin", "--manifest", "m.json"])
+        .args(extra)
+        .assert();
+    // min_S=43, thr=51: the margin is reported even without the flag
+    verify(&[]).success().stderr(predicates::str::contains("margin=-8"));
+    verify(&["--min-margin", "-8"])
+        .success()
+        .stderr(predicates::str::contains("min-margin: -8 >= -8 ok"));
+    verify(&["--min-margin", "0"])
+        .failure()
+        .stderr(predicates::str::contains("--min-margin 0: min_S - thr = -8 is below the required margin"));
+    bin().current_dir(dir.path())
+        .args(["verify", "--no-table", "--manifest", "m.json", "--min-margin", "-7"])
+        .assert()
+        .failure();
+    // --structural-only computes no threshold, so a margin cannot be checked there
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin", "--structural-only", "--min-margin", "1000"])
+        .assert()
+        .code(2)
+        .stderr(predicates::str::contains("cannot be used with"));
+    Ok(())
+}
+
+#[test]
+fn stats_approx_quantiles_stay_within_rank_bound() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "14", "--l", "48", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let out = bin().current_dir(dir.path())
+        .args(["stats", "--table", "t.bin", "--approx-quantiles", "--quantile-error", "0.02"])
+        .output()?;
+    assert!(out.status.success());
+    let text = String::from_utf8(out.stderr)?;
+    let mut sorted = read_v2_values(&dir.path().join("t.bin"))?;
+    sorted.sort_unstable();
+    let n = sorted.len();
+    let slack = (0.02 * n as f64).floor() as usize;
+    assert!(text.contains(&format!("rank within +/-{slack} of {n} entries")), "{text}");
+    assert!(!text.contains("fixed point"), "{text}");
+    let line = text.lines().find(|l| l.trim_start().starts_with("p1=")).expect("quantile line");
+    for item in line.split_whitespace() {
+        let (name, v) = item.split_once('=').unwrap();
+        let q: f64 = name[1..].parse::<f64>()? / 100.0;
+        let v: u32 = v.parse()?;
+        let rank = (q * n as f64).ceil() as usize;
+        // 1-based ranks occupied by v in the sorted table
+        let first = sorted.partition_point(|&x| x < v) + 1;
+        let last = sorted.partition_point(|&x| x <= v);
+        assert!(first <= last, "{name}={v} is not a table value");
+        assert!(first <= rank + slack && rank <= last + slack, "{name}={v}: ranks {first}..={last}, want {rank} +/- {slack}");
+    }
+
+    bin().current_dir(dir.path())
+        .args(["stats", "--table", "t.bin", "--approx-quantiles", "--plot"])
+        .assert()
+        .failure();
+    bin().current_dir(dir.path())
+        .args(["stats", "--table", "t.bin", "--approx-quantiles", "--quantile-error", "0.5"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn verify_rewrite_manifest_fills_fields_from_recompute() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--note", "keep me", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let orig: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.path().join("m.json"))?)?;
+    // an older-schema manifest: optional fields and the self-hash absent
+    let mut old = orig.clone();
+    for field in ["max_s", "mean_s", "neg_drift_count", "modulus_bits", "residue_form", "manifest_sha256"] {
+        old.as_object_mut().unwrap().remove(field);
+    }
+    std::fs::write(dir.path().join("old.json"), serde_json::to_string_pretty(&old)?)?;
+
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin", "--manifest", "old.json", "--rewrite-manifest", "new.json"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("rewrite-manifest: wrote new.json"));
+    let new: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.path().join("new.json"))?)?;
+    for field in ["max_s", "mean_s", "neg_drift_count", "modulus_bits", "residue_form", "sha256_table_hex", "min_s", "gen_ts", "notes"] {
+        assert_eq!(new[field], orig[field], "{field}");
+    }
+    assert!(new["manifest_sha256"].is_string());
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin", "--manifest", "new.json"])
+        .assert()
+        .success();
+
+    // a failed verify writes nothing
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin", "--manifest", "old.json", "--min-margin", "0", "--rewrite-manifest", "bad.json"])
+        .assert()
+        .failure();
+    assert!(!dir.path().join("bad.json").exists());
+    Ok(())
+}
+
+#[test]
+fn bundle_round_trips_and_isolates_corrupt_entries() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["sweep", "--k", "8,10", "--l", "16", "--out-dir", "s"])
+        .assert()
+        .success();
+    bin().current_dir(dir.path())
+        .args(["bundle", "s/table_k8_l16_v2.bin", "s/table_k10_l16_v2.bin", "--out", "b.calb"])
+        .assert()
+        .success();
+    bin().current_dir(dir.path())
+        .args(["verify-bundle", "--bundle", "b.calb"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("directory sha256 ok"))
+        .stderr(predicates::str::contains("manifest ok"));
+    bin().current_dir(dir.path())
+        .args(["unbundle", "--bundle", "b.calb", "--k", "10", "--l", "16", "--out-dir", "x"])
+        .assert()
+        .success();
+    assert_eq!(std::fs::read(dir.path().join("x/table_k10_l16_v2.bin"))?, std::fs::read(dir.path().join("s/table_k10_l16_v2.bin"))?);
+    assert!(!dir.path().join("x/table_k8_l16_v2.bin").exists());
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "x/table_k10_l16_v2.bin", "--manifest", "x/cert_k10_l16_v2.json"])
+        .assert()
+        .success();
+
+    // a flipped byte in the second table's body only fails that entry
+    let mut data = std::fs::read(dir.path().join("b.calb"))?;
+    let count = u32::from_le_bytes(data[8..12].try_into()?) as usize;
+    assert_eq!(count, 2);
+    let second = 16 + 104;
+    let offset = u64::from_le_bytes(data[second + 8..second + 16].try_into()?) as usize;
+    data[offset + 40] ^= 1;
+    std::fs::write(dir.path().join("bad.calb"), &data)?;
+    bin().current_dir(dir.path())
+        .args(["verify-bundle", "--bundle", "bad.calb"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("bundle entry k=10 l=16: table sha256 mismatch"))
+        .stderr(predicates::str::contains("1 entry failed"));
+    bin().current_dir(dir.path())
+        .args(["unbundle", "--bundle", "bad.calb", "--k", "8", "--l", "16", "--out-dir", "y"])
+        .assert()
+        .success();
+
+    // the directory is covered by its own sha256
+    data[second] ^= 1;
+    std::fs::write(dir.path().join("bad.calb"), &data)?;
+    bin().current_dir(dir.path())
+        .args(["verify-bundle", "--bundle", "bad.calb"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("bundle directory sha256 mismatch"));
+    Ok(())
+}
+
+#[test]
+fn gen_max_index_writes_table_flagged_partial() -> Result<(), Box<dyn std::error::Error>> {
+    use predicates::prelude::PredicateBooleanExt;
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "20", "--l", "32", "--max-index", "3000", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("partial: only residues [0, 3000) of 524288"));
+    let data = std::fs::read(dir.path().join("t.bin"))?;
+    assert_eq!(data.len(), 32 + 3000 * 4 + 32);
+    assert_eq!(u64::from_le_bytes(data[16..24].try_into()?), 3000);
+    assert_eq!(u32::from_le_bytes(data[24..28].try_into()?), collatz_cert::FLAG_PARTIAL);
+    let values = read_v2_values(&dir.path().join("t.bin"))?;
+    for (i, &v) in values.iter().enumerate().step_by(97) {
+        assert_eq!(v, collatz_s_sum(20, 32, i), "index {i}");
+    }
+    // the manifest never claims a pass for a partial table, and says how to rebuild this one
+    let mf: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.path().join("m.json"))?)?;
+    assert_eq!(mf["count"], 3000);
+    assert_eq!(mf["pass"], false);
+    assert_eq!(mf["reproduce_cmd"], "collatz_cert gen --k 20 --l 32 --max-index 3000");
+    bin().current_dir(dir.path())
+        .args(["validate-manifest", "--manifest", "m.json"])
+        .assert()
+        .success();
+    let mut claimed = mf.clone();
+    claimed["pass"] = serde_json::Value::Bool(true);
+    claimed.as_object_mut().unwrap().remove("manifest_sha256");
+    std::fs::write(dir.path().join("claimed.json"), serde_json::to_string_pretty(&claimed)?)?;
+    bin().current_dir(dir.path())
+        .args(["validate-manifest", "--manifest", "claimed.json"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("manifest pass=true but count=3000 covers only part of the 524288 residues"));
+
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin", "--manifest", "m.json"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("verify (partial table): all 3000 entries match, of 524288 residues"))
+        .stderr(predicates::str::contains("full pass not established"));
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin", "--manifest", "m.json", "--min-margin", "-100"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("is a partial table"));
+    bin().current_dir(dir.path())
+        .args(["stats", "--table", "t.bin"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("partial table: entries [0, 3000) of 524288 residues only"));
+
+    // gates that would read pass from a partial run are refused outright
+    for gate in ["--require-pass", "--summary-line", "--verify-after"] {
+        bin().current_dir(dir.path())
+            .args(["gen", "--k", "12", "--l", "8", "--max-index", "1", "--out-table", "g.bin", "--out-manifest", "g.json", gate])
+            .assert()
+            .failure();
+    }
+    // verify-all does not count a partial table as certified, and sweep regenerates it
+    std::fs::create_dir(dir.path().join("d"))?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "12", "--l", "8", "--max-index", "5",
+               "--out-table", "d/table_k12_l8_v2.bin", "--out-manifest", "d/cert_k12_l8_v2.json"])
+        .assert()
+        .success();
+    bin().current_dir(dir.path())
+        .args(["verify-all", "--dir", "d"])
+        .assert()
+        .failure()
+        .stdout(predicates::str::contains("partial table, not certified"))
+        .stdout(predicates::str::contains("1 checked, 1 failed"));
+    bin().current_dir(dir.path())
+        .args(["sweep", "--k", "12", "--l", "8", "--out-dir", "d", "--skip-existing"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("existing table is partial; regenerating"));
+    let swept = std::fs::read(dir.path().join("d/table_k12_l8_v2.bin"))?;
+    assert_eq!(u64::from_le_bytes(swept[16..24].try_into()?), 2048);
+    bin().current_dir(dir.path())
+        .args(["verify-all", "--dir", "d"])
+        .assert()
+        .stdout(predicates::str::contains("not certified").not());
+
+    // a cap at or past the residue count is an ordinary full table
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "6", "--l", "8", "--entries", "1000", "--out-table", "f.bin", "--out-manifest", "f.json"])
+        .assert()
+        .success();
+    let full = std::fs::read(dir.path().join("f.bin"))?;
+    assert_eq!(u32::from_le_bytes(full[24..28].try_into()?), 0);
+    Ok(())
+}
+
+#[test]
+fn stats_plot_draws_one_bar_per_bin() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "10", "--l", "32", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let out = bin().current_dir(dir.path())
+        .args(["stats", "--table", "t.bin", "--bins", "10", "--plot", "--width", "60"])
+        .output()?;
+    assert!(out.status.success());
+    let text = String::from_utf8(out.stderr)?;
+    let rows: Vec<&str> = text.lines().filter(|s| s.starts_with('[')).collect();
+    assert_eq!(rows.len(), 10, "{text}");
+    // stderr is a pipe here, so no colour codes
+    assert!(!text.contains('\x1b'), "{text}");
+    let mut total = 0;
+    for row in &rows {
+        assert!(row.len() <= 60, "{row}");
+        let n: usize = row.split_whitespace().nth(2).unwrap().parse()?;
+        assert_eq!(row.matches('#').count() > 0, n > 0, "{row}");
+        total += n;
+    }
+    assert_eq!(total, 512);
+    assert!(rows[8].starts_with("[67, 70) 136 ") && rows[8].trim_end().len() == 60, "{}", rows[8]);
+    Ok(())
+}
+
+#[test]
+fn verify_io_and_recompute_threads_are_separate() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    for (name, extra) in [("v2", &[][..]), ("v3", &["--with-state"][..])] {
+        let (t, m) = (format!("t_{name}.bin"), format!("m_{name}.json"));
+        bin().current_dir(dir.path())
+            .args(["gen", "--k", "10", "--l", "32", "--out-table", &t, "--out-manifest", &m])
+            .args(extra)
+            .assert()
+            .success();
+        bin().current_dir(dir.path())
+            .args(["verify", "--table", &t, "--manifest", &m, "--threads", "3", "--io-threads", "2"])
+            .assert()
+            .success()
+            .stderr(predicates::str::contains("threads: io=2 recompute=3"));
+        bin().current_dir(dir.path())
+            .args(["verify", "--table", &t, "--manifest", &m, "--threads", "2", "--recompute-threads", "1"])
+            .assert()
+            .success()
+            .stderr(predicates::str::contains("threads: io=2 recompute=1"));
+        // the read stage has two tasks, so a larger pool is clamped and says so
+        bin().current_dir(dir.path())
+            .args(["verify", "--table", &t, "--manifest", &m, "--io-threads", "8", "--recompute-threads", "1"])
+            .assert()
+            .success()
+            .stderr(predicates::str::contains("io-threads: 8 requested; the read stage is 2 tasks (sha256, decode), using at most 2"))
+            .stderr(predicates::str::contains("threads: io=2 recompute=1"));
+    }
+    Ok(())
+}
+
+#[test]
+fn equal_compares_headers_then_trailers() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let gen = |extra: &[&str], t: &str| {
+        bin().current_dir(dir.path())
+            .args(["gen", "--out-table", t, "--out-manifest", &format!("{t}.json")])
+            .args(extra)
+            .assert()
+            .success();
+    };
+    gen(&["--k", "10", "--l", "32"], "a.bin");
+    gen(&["--k", "10", "--l", "32"], "b.bin");
+    gen(&["--k", "10", "--l", "33"], "c.bin");
+    gen(&["--k", "10", "--l", "32", "--with-state"], "d.bin");
+    let mut gz = flate2::write::GzEncoder::new(File::create(dir.path().join("b.bin.gz"))?, flate2::Compression::default());
+    gz.write_all(&std::fs::read(dir.path().join("b.bin"))?)?;
+    gz.finish()?;
+    let equal = |a: &str, b: &str| bin().current_dir(dir.path()).args(["equal", "--a", a, "--b", b]).assert();
+
+    equal("a.bin", "b.bin.gz")
+        .success()
+        .stderr(predicates::str::contains("equal: identical (K=10 L=32 count=512"));
+    equal("a.bin", "c.bin")
+        .failure()
+        .stderr(predicates::str::contains("structurally different"));
+    equal("a.bin", "d.bin")
+        .failure()
+        .stderr(predicates::str::contains("equal: differ"))
+        .stderr(predicates::str::contains("the encodings differ (ver 2 vs 3)"));
+
+    // a flipped body byte fails that table's own trailer check before any comparison
+    let p = dir.path().join("b.bin");
+    let mut bytes = std::fs::read(&p)?;
+    bytes[40] ^= 1;
+    std::fs::write(&p, bytes)?;
+    equal("a.bin", "b.bin")
+        .failure()
+        .stderr(predicates::str::contains("table sha256 mismatch"));
+    Ok(())
+}
+
+#[test]
+fn collatz_k_l_env_supply_defaults() -> Result<(), Box<dyn std::error::Error>> {
+    use predicates::prelude::PredicateBooleanExt;
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .env("COLLATZ_K", "6").env("COLLATZ_L", "12")
+        .args(["gen", "--l", "10"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("note: k=6 from COLLATZ_K"))
+        .stderr(predicates::str::contains("COLLATZ_L").not());
+    assert!(dir.path().join("table_k6_l10_v2.bin").exists());
+    bin().current_dir(dir.path())
+        .env("COLLATZ_L", "12")
+        .args(["threshold"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("note: l=12 from COLLATZ_L"))
+        .stdout(predicates::str::contains("thr=20"));
+    bin().current_dir(dir.path())
+        .args(["trace", "--l", "8", "--index", "0"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--k is required (or set COLLATZ_K)"));
+    bin().current_dir(dir.path())
+        .env("COLLATZ_K", "six")
+        .args(["bench", "--l", "8"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("COLLATZ_K=\"six\""));
+    Ok(())
+}
+
+#[test]
+fn verify_no_table_recomputes_from_manifest() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    for (m, extra) in [("m.json", &[][..]), ("e.json", &["--include-even"][..])] {
+        bin().current_dir(dir.path())
+            .args(["gen", "--k", "10", "--l", "32", "--out-table", &format!("{m}.bin"), "--out-manifest", m])
+            .args(extra)
+            .assert()
+            .success();
+        std::fs::remove_file(dir.path().join(format!("{m}.bin")))?;
+        bin().current_dir(dir.path())
+            .args(["verify", "--manifest", m, "--no-table"])
+            .assert()
+            .success()
+            .stderr(predicates::str::contains("verify (no table): min_S=43"))
+            .stderr(predicates::str::contains("table sha256 not checked"));
+    }
+
+    let mf_path = dir.path().join("m.json");
+    let mut v: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&mf_path)?)?;
+    v.as_object_mut().unwrap().remove("manifest_sha256");
+    v["min_s"] = 44.into();
+    std::fs::write(&mf_path, v.to_string())?;
+    bin().current_dir(dir.path())
+        .args(["verify", "--manifest", "m.json", "--no-table"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("manifest min_s mismatch: manifest=44 computed=43"));
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin", "--manifest", "m.json", "--no-table"])
+        .assert()
+        .failure();
+    // options the no-table path cannot honour are refused rather than ignored
+    for extra in [&["--expect-min-s", "999"][..], &["--k", "9"], &["--l", "99"], &["--explain"], &["--summary-line"],
+                  &["--metrics", "x.prom"], &["--compare-manifest", "e.json"], &["--verify-build"], &["--events-json", "ev.jsonl"]] {
+        bin().current_dir(dir.path())
+            .args(["verify", "--manifest", "e.json", "--no-table"])
+            .args(extra)
+            .assert()
+            .code(2)
+            .stderr(predicates::str::contains("cannot be used with"));
+    }
+    Ok(())
+}
+
+#[test]
+fn find_boundary_matches_gen_on_both_sides() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let out = bin().current_dir(dir.path())
+        .args(["find-boundary", "--k", "8", "--l-max", "30", "--out-csv", "p.csv"])
+        .output()?;
+    assert!(out.status.success());
+    let stdout = String::from_utf8(out.stdout)?;
+    let rows: Vec<Vec<&str>> = stdout.lines().map(|s| s.split_whitespace().collect()).collect();
+    assert_eq!(rows, [["L=1", "min_S=1", "thr=2", "pass=false"], ["L=25", "min_S=40", "thr=40", "pass=true"]]);
+    assert!(String::from_utf8(out.stderr)?.contains("boundary: pass flips false -> true at L=25"));
+
+    // every profile row agrees with a full gen at that L
+    let csv = std::fs::read_to_string(dir.path().join("p.csv"))?;
+    assert_eq!(csv.lines().count(), 31);
+    for l in [24u32, 25, 30] {
+        let s_min = (0..128).map(|i| collatz_s_sum(8, l, i)).min().unwrap();
+        let line = csv.lines().nth(l as usize).unwrap();
+        assert!(line.starts_with(&format!("{l},{s_min},")), "{line}");
+    }
+    Ok(())
+}
+
+#[test]
+fn pack_manifest_only_leaves_table_out() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "4", "--l", "8", "--threads", "1"])
+        .assert()
+        .success();
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "4", "--l", "9", "--threads", "1"])
+        .assert()
+        .success();
+    bin().current_dir(dir.path())
+        .args(["pack", "--manifest", "cert_k4_l8_v2.json", "--manifest-only", "--table", "table_k4_l8_v2.bin"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("matches the manifest's sha256_table_hex"))
+        .stdout(predicates::str::contains("manifest sha256="));
+    let gz = flate2::read::GzDecoder::new(File::open(dir.path().join("cert_k4_l8_v2_manifest.tar.gz"))?);
+    let mut names = Vec::new();
+    for e in tar::Archive::new(gz).entries()? {
+        names.push(e?.path()?.to_string_lossy().into_owned());
+    }
+    assert_eq!(names, ["cert_k4_l8_v2.json"]);
+
+    bin().current_dir(dir.path())
+        .args(["pack", "--manifest", "cert_k4_l8_v2.json", "--manifest-only", "--out", "m.tar.gz"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("warning: no --table given"));
+    bin().current_dir(dir.path())
+        .args(["pack", "--manifest", "cert_k4_l8_v2.json", "--manifest-only", "--table", "table_k4_l9_v2.bin", "--out", "x.tar.gz"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("is not the manifest's table"));
+    assert!(!dir.path().join("x.tar.gz").exists());
+    Ok(())
+}
+
+#[test]
+fn error_format_json_reports_kind_on_stdout() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "6", "--l", "12", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let mf_path = dir.path().join("m.json");
+    let mut v: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&mf_path)?)?;
+    v.as_object_mut().unwrap().remove("manifest_sha256");
+    v["min_s"] = 1.into();
+    std::fs::write(&mf_path, v.to_string())?;
+    std::fs::write(dir.path().join("bad.json"), "{not json")?;
+
+    for (args, kind, needle) in [
+        (&["verify", "--table", "t.bin", "--manifest", "m.json"][..], "verification", "manifest min_s mismatch"),
+        (&["verify", "--table", "missing.bin", "--manifest", "m.json"][..], "io", "missing.bin"),
+        (&["validate-manifest", "--manifest", "bad.json"][..], "parse", ""),
+        (&["gen", "--k", "40", "--l", "8"][..], "error", "k in [2,28]"),
+    ] {
+        let out = bin().current_dir(dir.path()).args(["--error-format", "json"]).args(args).output()?;
+        assert_eq!(out.status.code(), Some(1), "{args:?}");
+        let stdout = String::from_utf8(out.stdout)?;
+        assert_eq!(stdout.lines().count(), 1, "{stdout}");
+        let err: serde_json::Value = serde_json::from_str(&stdout)?;
+        assert_eq!(err["kind"], kind, "{stdout}");
+        assert!(err["error"].as_str().unwrap().contains(needle), "{stdout}");
+        assert!(!String::from_utf8(out.stderr)?.contains("Error:"));
+    }
+    Ok(())
+}
+
+#[test]
+fn prefetch_hint_is_logged_and_harmless() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "8", "--l", "16", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let expected = if cfg!(target_os = "linux") { "prefetch: sequential read-ahead hint applied" } else { "prefetch: not supported" };
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin", "--manifest", "m.json", "--prefetch"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(expected));
+    bin().current_dir(dir.path())
+        .args(["stats", "--table", "t.bin", "--prefetch"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(expected));
+    // stdin cannot take the hint
+    bin().current_dir(dir.path())
+        .args(["stats", "--table", "-", "--prefetch"])
+        .stdin(File::open(dir.path().join("t.bin"))?)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("prefetch: not supported for -"));
+    Ok(())
+}
+
+#[test]
+fn table_builder_matches_gen_and_compact_bytes() -> Result<(), Box<dyn std::error::Error>> {
+    use collatz_cert::{TableBuilder, VER_RLE};
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "6", "--l", "12", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    bin().current_dir(dir.path())
+        .args(["compact", "--table", "t.bin", "--out", "t4.bin"])
+        .assert()
+        .success();
+    let values = read_v2_values(&dir.path().join("t.bin"))?;
+    for (ver, file) in [(2, "t.bin"), (VER_RLE, "t4.bin")] {
+        let mut built = Vec::new();
+        let digest = TableBuilder::new(6, 12).version(ver).write(&mut built, values.iter().copied())?;
+        assert_eq!(built, std::fs::read(dir.path().join(file))?, "ver={ver}");
+        assert_eq!(&built[built.len() - 32..], digest);
+    }
+    let err = TableBuilder::new(6, 12).write(&mut Vec::new(), values[1..].iter().copied()).unwrap_err();
+    assert!(err.to_string().contains("31 values for count=32"), "{err}");
+    Ok(())
+}
+
+#[test]
+fn bit_analysis_exponents_sum_to_table_s() -> Result<(), Box<dyn std::error::Error>> {
+    let out = bin().args(["bit-analysis", "--k", "10", "--l", "32"]).output()?;
+    assert!(out.status.success());
+    let csv = String::from_utf8(out.stdout)?;
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("exponent,count"));
+    let (mut steps, mut s_total) = (0u64, 0u64);
+    for line in lines {
+        let (e, n) = line.split_once(',').unwrap();
+        let (e, n): (u64, u64) = (e.parse()?, n.parse()?);
+        assert!(e >= 1);
+        steps += n;
+        s_total += e * n;
+    }
+    assert_eq!(steps, 512 * 32);
+    let expected: u64 = (0..512).map(|i| collatz_s_sum(10, 32, i) as u64).sum();
+    assert_eq!(s_total, expected);
+    assert!(String::from_utf8(out.stderr)?.contains(&format!("mean_e={:.6}", expected as f64 / steps as f64)));
+    Ok(())
+}
+
+#[test]
+fn import_validates_then_round_trips_export() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    bin().current_dir(dir.path())
+        .args(["gen", "--k", "6", "--l", "12", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .assert()
+        .success();
+    let out = bin().current_dir(dir.path()).args(["export", "--table", "t.bin"]).output()?;
+    assert!(out.status.success());
+    let csv = String::from_utf8(out.stdout)?;
+    // rows in reverse order are still a complete table
+    let mut rows: Vec<&str> = csv.lines().skip(1).collect();
+    rows.reverse();
+    std::fs::write(dir.path().join("rev.csv"), rows.join("\n"))?;
+    let import = |file: &str, extra: &[&str]| bin().current_dir(dir.path())
+        .args(["import", "--csv", file, "--k", "6", "--l", "12"])
+        .args(extra)
+        .assert();
+    import("rev.csv", &["--validate-only"])
+        .success()
+        .stderr(predicates::str::contains("32 rows cover [0,32) exactly once"));
+    assert_eq!(std::fs::read_dir(dir.path())?.count(), 3);
+    import("rev.csv", &["--out", "back.bin"]).success();
+    assert_eq!(std::fs::read(dir.path().join("back.bin"))?, std::fs::read(dir.path().join("t.bin"))?);
+
+    for (body, needle) in [
+        ("0,5\n0,6\n", "rev_dup.csv:2: duplicate index 0 (first on line 1)"),
+        ("0,-1\n", "value -1 at index 0 is not a u32"),
+        ("0,4294967296\n", "is not a u32"),
+        ("32,1\n", "index 32 out of range"),
+        ("index,s\n0,1\n", "index 1 is missing (1 of 32 entries present)"),
+    ] {
+        std::fs::write(dir.path().join("rev_dup.csv"), body)?;
+        import("rev_dup.csv", &["--validate-only"])
+            .failure()
+            .stderr(predicates::str::contains(needle));
+    }
+    Ok(())
+}
+
+#[test]
+fn gen_reports_peak_rss() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempdir()?;
+    let out = bin().current_dir(dir.path())
+        .args(["gen", "--k", "8", "--l", "16", "--out-table", "t.bin", "--out-manifest", "m.json"])
+        .output()?;
+    assert!(out.status.success());
+    let stderr = String::from_utf8(out.stderr)?;
+    let mf: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.path().join("m.json"))?)?;
+    if cfg!(unix) {
+        let rss = mf["peak_rss_bytes"].as_u64().expect("peak_rss_bytes on unix");
+        // at least the binary itself is resident
+        assert!(rss > 1 << 20, "{rss}");
+        assert!(stderr.contains(&format!("({rss} bytes)")), "{stderr}");
+    } else {
+        assert!(mf.get("peak_rss_bytes").is_none());
+        assert!(stderr.contains("peak RSS: unknown"));
+    }
+    bin().current_dir(dir.path())
+        .args(["verify", "--table", "t.bin", "--manifest", "m.json"])
+        .assert()
+        .success();
+    Ok(())
+}